@@ -0,0 +1,56 @@
+//! Throughput of loading quads into DuckDB.
+//!
+//! Run with `cargo bench --bench load`.
+
+use cottas_rs::duckdb::{connection_in_memory, load_into_duckdb, stream_into_duckdb};
+use cottas_rs::parser::RdfQuad;
+use std::time::{Duration, Instant};
+
+const QUADS: usize = 500_000;
+const RUNS: usize = 3;
+
+/// Builds `n` quads over 1000 subjects and 10 predicates, half of them in a named graph.
+fn quads(n: usize) -> Vec<RdfQuad> {
+    (0..n)
+        .map(|i| {
+            (
+                format!("<http://example.org/s{}>", i % 1000),
+                format!("<http://example.org/p{}>", i % 10),
+                format!("\"{}\"", i),
+                (i % 2 == 0).then(|| format!("<http://example.org/g{}>", i % 5)),
+            )
+        })
+        .collect()
+}
+
+/// Runs `load` `RUNS` times and prints the best time and throughput.
+fn bench(name: &str, quads: &[RdfQuad], load: impl Fn(&[RdfQuad])) {
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            load(quads);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+
+    println!(
+        "{:<20} {} quads in {:?} ({:.0} quads/s)",
+        name,
+        quads.len(),
+        best,
+        quads.len() as f64 / best.as_secs_f64()
+    );
+}
+
+fn main() {
+    let quads = quads(QUADS);
+
+    bench("load_into_duckdb", &quads, |quads| {
+        load_into_duckdb(quads).unwrap();
+    });
+    bench("stream_into_duckdb", &quads, |quads| {
+        let conn = connection_in_memory().unwrap();
+        stream_into_duckdb(&conn, quads.iter().map(Ok)).unwrap();
+    });
+}
@@ -1,16 +1,30 @@
 //! DuckDB integration utilities for Cottas: loading, querying, and managing Parquet-based RDF data.
 
+use crate::error::CottasError;
+use crate::export::{
+    check_sparql_deletions, write_changeset_to_file, write_checked_changeset, ChangesetFormat,
+    CompressedWriter,
+};
 pub use crate::parser::*;
-use crate::utils::build_order_by;
 pub use crate::utils::is_valid_index;
+use crate::utils::{
+    build_kv_metadata_inherited, build_order_by, build_parquet_options, check_index,
+    sql_identifier, sql_string, Compression, WriteOptions,
+};
 use chrono::{DateTime, Utc};
-use duckdb::{Connection, OptionalExt, ToSql};
+use duckdb::{params, Connection, OptionalExt};
+use oxrdf::{Quad, Term};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::error::Error;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use url::Url;
 
-/// Loads a vector of RDF quads into an in-memory DuckDB database.
+/// Loads a slice of RDF quads into an in-memory DuckDB database.
 ///
 /// # Arguments
 ///
@@ -18,282 +32,1462 @@ use std::fs;
 ///
 /// # Returns
 ///
-/// * `Connection` - An in-memory DuckDB connection with the loaded data.
-pub fn load_into_duckdb(quads: &[(String, String, String, Option<String>)]) -> Connection {
-    let conn = connection_in_memory();
+/// * `Ok(Connection)` - An in-memory DuckDB connection with the loaded data.
+pub fn load_into_duckdb(quads: &[RdfQuad]) -> Result<Connection, CottasError> {
+    let conn = connection_in_memory()?;
+    stream_into_duckdb(&conn, quads.iter().map(Ok))?;
+    Ok(conn)
+}
+
+/// Streams RDF quads into the `quads` table of a DuckDB database without collecting them.
+///
+/// Quads are bulk loaded through a DuckDB [`Appender`](duckdb::Appender), which buffers rows
+/// in fixed-size chunks, so the memory usage does not depend on the number of quads. Combined
+/// with [`connection_on_disk`], this allows loading inputs larger than the available memory.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `quads` - An iterator of subject, predicate, object, and optional graph tuples.
+///
+/// # Returns
+///
+/// * `Ok(true)` if at least one quad belongs to a named graph, `Ok(false)` otherwise.
+pub fn stream_into_duckdb<I, Q>(conn: &Connection, quads: I) -> Result<bool, CottasError>
+where
+    I: IntoIterator<Item = Result<Q, CottasError>>,
+    Q: Borrow<RdfQuad>,
+{
+    conn.execute("CREATE TABLE quads (s TEXT, p TEXT, o TEXT, g TEXT)", [])?;
+
+    let mut appender = conn.appender("quads")?;
+    let mut quad_mode = false;
+
+    for quad in quads {
+        let quad = quad?;
+        let (s, p, o, g) = quad.borrow();
+        quad_mode |= g.is_some();
+
+        appender.append_row(params![s, p, o, g])?;
+    }
+    appender.flush()?;
+
+    Ok(quad_mode)
+}
+
+/// Creates a DuckDB connection backed by a database file inside `dir`.
+///
+/// DuckDB also uses `dir` to spill intermediate results (e.g., when sorting), so large
+/// datasets do not need to fit in memory.
+///
+/// # Arguments
+///
+/// * `dir` - Directory holding the database file and temporary files.
+///
+/// # Returns
+///
+/// * `Ok(Connection)` - A DuckDB connection to the on-disk database.
+pub fn connection_on_disk(dir: &Path) -> Result<Connection, CottasError> {
+    let conn = Connection::open(dir.join("cottas.duckdb"))?;
+
+    let temp_dir = dir.join("spill");
+    conn.execute_batch(&format!(
+        "SET temp_directory = {}; SET preserve_insertion_order = false;",
+        sql_string(&temp_dir.to_string_lossy())
+    ))?;
+
+    Ok(conn)
+}
+
+/// Creates a new in-memory DuckDB connection.
+///
+/// # Returns
+///
+/// * `Ok(Connection)` - An in-memory DuckDB connection.
+///
+/// # Errors
+///
+/// Returns an error if DuckDB cannot create the database.
+pub fn connection_in_memory() -> Result<Connection, CottasError> {
+    Ok(Connection::open_in_memory()?)
+}
+
+/// Checks if a Parquet file contains a specific column.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `column` - The column name to check.
+///
+/// # Returns
+///
+/// * `Ok(true)` if the column exists, `Ok(false)` otherwise.
+pub fn has_column(
+    conn: &Connection,
+    cottas_file_path: &str,
+    column: &str,
+) -> Result<bool, CottasError> {
+    let mut stmt = conn.prepare("SELECT name FROM PARQUET_SCHEMA(?)")?;
+    let mut rows = stmt.query([cottas_file_path])?;
+
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Parses a triple or quad pattern for a Cottas file.
+///
+/// Prefixed names (e.g., `foaf:name`) are resolved against the prefixes stored in the
+/// key-value metadata of the file, which is only read if the pattern needs them.
+fn parse_file_pattern(
+    conn: &Connection,
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<String>, CottasError> {
+    if let Ok(tp_tuple) = parse_tp(triple_pattern) {
+        return Ok(tp_tuple);
+    }
+
+    let namespaces = read_namespaces(conn, &[cottas_file_path])?;
+    parse_tp_with_namespaces(triple_pattern, &namespaces)
+}
+
+/// Reads the prefixes and base IRI stored in the key-value metadata of Cottas files.
+///
+/// When several files declare the same prefix or a base IRI, the first file wins.
+pub(crate) fn read_namespaces<P: AsRef<str>>(
+    conn: &Connection,
+    cottas_file_paths: &[P],
+) -> Result<Namespaces, CottasError> {
+    let mut namespaces = Namespaces::default();
+    for file in cottas_file_paths {
+        let file_namespaces = Namespaces::from_kv_metadata(&read_kv_metadata(conn, file.as_ref())?);
+        for (name, iri) in file_namespaces.prefixes {
+            namespaces.prefixes.entry(name).or_insert(iri);
+        }
+        if namespaces.base_iri.is_none() {
+            namespaces.base_iri = file_namespaces.base_iri;
+        }
+    }
+
+    Ok(namespaces)
+}
+
+/// Translates a triple or quad pattern into a DuckDB SQL query.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The generated SQL query.
+///
+/// # Errors
+///
+/// Returns an error if the pattern is malformed.
+pub fn translate_triple_pattern(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<String, CottasError> {
+    // Parse the triple pattern
+    let conn = connection_in_memory()?;
+    let tp_tuple = parse_file_pattern(&conn, cottas_file_path, triple_pattern)?;
+
+    Ok(translate_tp_tuple(cottas_file_path, &tp_tuple))
+}
+
+/// Translates the terms of a parsed triple or quad pattern into a DuckDB SQL query.
+pub(crate) fn translate_tp_tuple(cottas_file_path: &str, tp_tuple: &[String]) -> String {
+    translate_tp_tuple_in_row_groups(cottas_file_path, tp_tuple, None)
+}
+
+/// Translates a parsed triple or quad pattern into a DuckDB SQL query that only reads the row
+/// groups of the file that may contain matches (see [`prune_row_groups`]).
+///
+/// Patterns without constants read the whole file.
+pub(crate) fn translate_pruned_tp_tuple(
+    conn: &Connection,
+    cottas_file_path: &str,
+    tp_tuple: &[String],
+) -> Result<String, CottasError> {
+    if !has_constants(tp_tuple) {
+        return Ok(translate_tp_tuple(cottas_file_path, tp_tuple));
+    }
+
+    let row_groups = prune_row_groups(conn, cottas_file_path, tp_tuple)?;
+    Ok(translate_tp_tuple_in_row_groups(
+        cottas_file_path,
+        tp_tuple,
+        Some(&row_groups),
+    ))
+}
+
+/// Returns true if a parsed triple or quad pattern has a constant term, so that its row groups
+/// can be pruned.
+fn has_constants(tp_tuple: &[String]) -> bool {
+    tp_tuple.iter().any(|term| !is_variable(term))
+}
+
+/// Translates a parsed triple or quad pattern into a DuckDB SQL query, restricted to the rows
+/// of `row_groups` if given.
+fn translate_tp_tuple_in_row_groups(
+    cottas_file_path: &str,
+    tp_tuple: &[String],
+    row_groups: Option<&[RowGroup]>,
+) -> String {
+    let select_clause = if tp_tuple.len() == 3 {
+        "SELECT s, p, o"
+    } else {
+        "SELECT s, p, o, g"
+    };
+
+    // Start building query
+    let mut query = match row_groups {
+        Some(row_groups) => format!(
+            "{} FROM PARQUET_SCAN({}, file_row_number = true) WHERE {} AND ",
+            select_clause,
+            sql_string(cottas_file_path),
+            row_number_condition(row_groups)
+        ),
+        None => format!(
+            "{} FROM PARQUET_SCAN({}) WHERE ",
+            select_clause,
+            sql_string(cottas_file_path)
+        ),
+    };
+
+    // Build WHERE clause - iterate over all positions
+    let mut variable_positions: HashMap<&str, usize> = HashMap::new();
+    for (i, term) in tp_tuple.iter().enumerate() {
+        if is_variable(term) {
+            // Repeated variables must bind the same term in every position
+            match variable_positions.get(&term[1..]) {
+                Some(&first) => query.push_str(&format!("{}={} AND ", I_POS[i], I_POS[first])),
+                None => {
+                    variable_positions.insert(&term[1..], i);
+                }
+            }
+            // A graph variable only matches named graphs
+            if i == 3 {
+                query.push_str("g IS NOT NULL AND ");
+            }
+        } else {
+            query.push_str(&format!("{}={} AND ", I_POS[i], sql_string(term)));
+        }
+    }
+
+    // Clean up trailing 'AND ' or 'WHERE '
+    if query.ends_with("AND ") {
+        query.truncate(query.len() - 4);
+    }
+    if query.ends_with("WHERE ") {
+        query.truncate(query.len() - 6);
+    }
+
+    query
+}
+
+/// Builds the condition on `file_row_number` that selects the rows of the given row groups.
+///
+/// Adjacent row groups are merged into one range. Since files are sorted by their index, the
+/// row groups matching a pattern that binds the leading terms of the index are adjacent.
+fn row_number_condition(row_groups: &[RowGroup]) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for row_group in row_groups {
+        let end = row_group.first_row + row_group.num_rows;
+        match ranges.last_mut() {
+            Some((_, last_end)) if *last_end == row_group.first_row => *last_end = end,
+            _ => ranges.push((row_group.first_row, end)),
+        }
+    }
+
+    if ranges.is_empty() {
+        return "FALSE".to_string();
+    }
+    let conditions = ranges
+        .iter()
+        .map(|(start, end)| format!("file_row_number BETWEEN {} AND {}", start, end - 1))
+        .collect::<Vec<_>>();
+    format!("({})", conditions.join(" OR "))
+}
+
+/// A row group of a Parquet file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowGroup {
+    /// Position of the row group in the file, starting at 0.
+    pub id: u64,
+    /// Position in the file of the first row of the row group, starting at 0.
+    pub first_row: u64,
+    /// Number of rows of the row group.
+    pub num_rows: u64,
+}
+
+/// Reads the row groups of a Parquet file that may contain matches of a parsed pattern.
+///
+/// A row group is skipped if the min/max statistics of a column exclude the constant of the
+/// pattern at that position, or if the bloom filter of the column excludes it. Only the
+/// Parquet metadata is read. Searches restrict their scan to the rows of the returned row
+/// groups, and count estimates sum their rows.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `tp_tuple` - The terms of the pattern, as returned by [`parse_tp`].
+///
+/// # Returns
+///
+/// * `Ok(Vec<RowGroup>)` - The row groups that may match, in file order.
+pub fn prune_row_groups(
+    conn: &Connection,
+    cottas_file_path: &str,
+    tp_tuple: &[String],
+) -> Result<Vec<RowGroup>, CottasError> {
+    let file = sql_string(cottas_file_path);
+    let constants = tp_tuple
+        .iter()
+        .enumerate()
+        .filter(|(_, term)| !is_variable(term))
+        .map(|(i, term)| (I_POS[i], sql_string(term)))
+        .collect::<Vec<_>>();
+
+    // A row group may contain a constant only if it lies between the column min and max
+    let condition = if constants.is_empty() {
+        "TRUE".to_string()
+    } else {
+        constants
+            .iter()
+            .map(|(column, value)| {
+                format!(
+                    "(path_in_schema <> '{column}' OR (COALESCE(stats_min_value <= {value}, TRUE) \
+                     AND COALESCE(stats_max_value >= {value}, TRUE)))"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    };
+
+    let query = format!(
+        "SELECT row_group_id, ANY_VALUE(row_group_num_rows), BOOL_AND({})
+        FROM PARQUET_METADATA({})
+        GROUP BY row_group_id
+        ORDER BY row_group_id",
+        condition, file
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, bool>(2)?,
+        ))
+    })?;
+
+    let mut row_groups = Vec::new();
+    let mut first_row = 0;
+    for row in rows {
+        let (id, num_rows, may_match) = row?;
+        if may_match {
+            row_groups.push(RowGroup {
+                id: id as u64,
+                first_row,
+                num_rows: num_rows as u64,
+            });
+        }
+        first_row += num_rows as u64;
+    }
+
+    // Drop the row groups whose bloom filter excludes a constant
+    for (column, value) in &constants {
+        if row_groups.is_empty() {
+            break;
+        }
+        let query = format!(
+            "SELECT row_group_id FROM PARQUET_BLOOM_PROBE({}, {}, {}) WHERE bloom_filter_excludes",
+            file,
+            sql_string(column),
+            value
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let excluded = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<Result<HashSet<_>, _>>()?;
+        row_groups.retain(|row_group| !excluded.contains(&(row_group.id as i64)));
+    }
+
+    Ok(row_groups)
+}
+
+/// Number of rows buffered between DuckDB and the consumer of a [`SearchIter`].
+const SEARCH_BUFFER_SIZE: usize = 1024;
+
+/// Iterator over the matches of a triple or quad pattern, yielded as DuckDB produces them.
+///
+/// The query runs on a background thread that owns the DuckDB connection. At most
+/// [`SEARCH_BUFFER_SIZE`] rows are buffered, and dropping the iterator stops the query. If the
+/// thread panics, the iterator yields a [`CottasError::WorkerPanicked`] error instead of
+/// ending early.
+pub struct SearchIter {
+    receiver: Receiver<Result<Vec<String>, CottasError>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Iterator for SearchIter {
+    type Item = Result<Vec<String>, CottasError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(row) => Some(row),
+            // The worker dropped its sender, either because it finished or because it panicked
+            Err(_) => match self.worker.take()?.join() {
+                Ok(()) => None,
+                Err(panic) => Some(Err(CottasError::worker_panicked(panic.as_ref()))),
+            },
+        }
+    }
+}
+
+/// Runs a search query and collects its rows.
+pub(crate) fn search_rows(
+    conn: &Connection,
+    sql: &str,
+    column_count: usize,
+) -> Result<Vec<Vec<String>>, CottasError> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |row| {
+        (0..column_count)
+            .map(|i| row.get::<_, String>(i))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Runs a search query and sends every row to `sender` until the receiver is dropped.
+fn send_search_rows(
+    conn: &Connection,
+    sql: &str,
+    column_count: usize,
+    sender: &SyncSender<Result<Vec<String>, CottasError>>,
+) -> Result<(), CottasError> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let mut result = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            let val: String = row.get(i)?;
+            result.push(val);
+        }
+        if sender.send(Ok(result)).is_err() {
+            // The iterator was dropped, stop early
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lazily searches for matches of a triple or quad pattern in a Parquet file using DuckDB.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(SearchIter)` - An iterator over the matching rows as vectors of strings.
+pub fn search_iter_in_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<SearchIter, CottasError> {
+    let conn = connection_in_memory()?;
+    let tp_tuple = parse_file_pattern(&conn, cottas_file_path, triple_pattern)?;
+    let sql = translate_pruned_tp_tuple(&conn, cottas_file_path, &tp_tuple)?;
+
+    Ok(search_iter_with_connection(conn, sql, tp_tuple.len()))
+}
+
+/// Runs a search query on a background thread that owns `conn`.
+pub(crate) fn search_iter_with_connection(
+    conn: Connection,
+    sql: String,
+    column_count: usize,
+) -> SearchIter {
+    let (sender, receiver) = sync_channel(SEARCH_BUFFER_SIZE);
+    let worker = thread::spawn(move || {
+        if let Err(e) = send_search_rows(&conn, &sql, column_count, &sender) {
+            let _ = sender.send(Err(e));
+        }
+    });
+
+    SearchIter {
+        receiver,
+        worker: Some(worker),
+    }
+}
+
+/// Searches for matches of a triple or quad pattern in a Parquet file using DuckDB.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<String>>)` - Query results as vectors of strings.
+pub fn search_in_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<Vec<String>>, CottasError> {
+    search_iter_in_duckdb(cottas_file_path, triple_pattern)?.collect()
+}
+
+/// Estimates the number of matches of a triple or quad pattern from Parquet metadata.
+///
+/// Only the row group statistics and bloom filters are read: row groups that cannot contain a
+/// constant of the pattern are skipped (see [`prune_row_groups`]), and the rows of the
+/// remaining row groups are counted. The estimate is therefore an upper bound of the exact
+/// count.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(u64)` - Estimated number of matches.
+pub fn estimate_count_in_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<u64, CottasError> {
+    let conn = connection_in_memory()?;
+    let tp_tuple = parse_file_pattern(&conn, cottas_file_path, triple_pattern)?;
+
+    estimate_count_with_connection(&conn, cottas_file_path, &tp_tuple)
+}
+
+/// Estimates the number of matches of a parsed triple or quad pattern from Parquet metadata.
+pub(crate) fn estimate_count_with_connection(
+    conn: &Connection,
+    cottas_file_path: &str,
+    tp_tuple: &[String],
+) -> Result<u64, CottasError> {
+    // Patterns without constants match every row
+    if !has_constants(tp_tuple) {
+        let rows: Option<i64> = conn
+            .query_row(
+                &format!(
+                    "SELECT num_rows FROM PARQUET_FILE_METADATA({})",
+                    sql_string(cottas_file_path)
+                ),
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        return Ok(rows.unwrap_or(0) as u64);
+    }
+
+    Ok(prune_row_groups(conn, cottas_file_path, tp_tuple)?
+        .iter()
+        .map(|row_group| row_group.num_rows)
+        .sum())
+}
+
+/// Searches for matches of a triple or quad pattern and returns the bindings of its variables.
+///
+/// Variables are keyed by their name without the leading `?` or `$`.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(Vec<HashMap<String, Term>>)` - One solution mapping per match.
+pub fn search_bindings_in_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<HashMap<String, Term>>, CottasError> {
+    let conn = connection_in_memory()?;
+    let tp_tuple = parse_file_pattern(&conn, cottas_file_path, triple_pattern)?;
+    let sql = translate_pruned_tp_tuple(&conn, cottas_file_path, &tp_tuple)?;
+
+    search_rows(&conn, &sql, tp_tuple.len())?
+        .into_iter()
+        .map(|row| {
+            let mut bindings = HashMap::new();
+            for (term, value) in tp_tuple.iter().zip(&row) {
+                if is_variable(term) {
+                    bindings.insert(term[1..].to_string(), parse_term(value)?);
+                }
+            }
+            Ok(bindings)
+        })
+        .collect()
+}
+
+/// Translates a basic graph pattern into a DuckDB SQL query joining one scan per pattern.
+///
+/// # Arguments
+///
+/// * `cottas_file_paths` - Paths to the Parquet files queried as a single dataset.
+/// * `bgp` - The triple or quad patterns, as returned by [`parse_bgp`].
+///
+/// # Returns
+///
+/// * `(String, Vec<String>)` - The generated SQL query and the names of the selected variables.
+pub fn translate_bgp(cottas_file_paths: &[String], bgp: &[Vec<String>]) -> (String, Vec<String>) {
+    let parquet_files = cottas_file_paths
+        .iter()
+        .map(|p| sql_string(p))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut variables: Vec<String> = Vec::new();
+    let mut variable_columns: HashMap<&str, String> = HashMap::new();
+    let mut from = Vec::with_capacity(bgp.len());
+    let mut conditions = Vec::new();
+
+    for (t, tp_tuple) in bgp.iter().enumerate() {
+        from.push(format!(
+            "PARQUET_SCAN([{}], union_by_name = true) AS t{}",
+            parquet_files, t
+        ));
+
+        for (i, term) in tp_tuple.iter().enumerate() {
+            let column = format!("t{}.{}", t, I_POS[i]);
+
+            if is_variable(term) {
+                // Shared variables join the patterns
+                match variable_columns.get(&term[1..]) {
+                    Some(first) => conditions.push(format!("{}={}", column, first)),
+                    None => {
+                        variables.push(term[1..].to_string());
+                        variable_columns.insert(&term[1..], column.clone());
+                    }
+                }
+                // A graph variable only matches named graphs
+                if i == 3 {
+                    conditions.push(format!("{} IS NOT NULL", column));
+                }
+            } else {
+                conditions.push(format!("{}={}", column, sql_string(term)));
+            }
+        }
+    }
+
+    let select = if variables.is_empty() {
+        "1".to_string()
+    } else {
+        variables
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{} AS {}",
+                    variable_columns[variable.as_str()],
+                    sql_identifier(variable)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut query = format!("SELECT {} FROM {}", select, from.join(", "));
+    if !conditions.is_empty() {
+        query.push_str(" WHERE ");
+        query.push_str(&conditions.join(" AND "));
+    }
+
+    (query, variables)
+}
+
+/// Evaluates a basic graph pattern over one or more Parquet files using DuckDB.
+///
+/// # Arguments
+///
+/// * `cottas_file_paths` - Paths to the Parquet files queried as a single dataset.
+/// * `bgp_str` - The basic graph pattern, triple or quad patterns separated by `.`.
+///
+/// # Returns
+///
+/// * `Ok(Vec<HashMap<String, Term>>)` - One solution mapping per match, keyed by variable name.
+pub fn bgp_in_duckdb(
+    cottas_file_paths: &[String],
+    bgp_str: &str,
+) -> Result<Vec<HashMap<String, Term>>, CottasError> {
+    let conn = connection_in_memory()?;
+    // Prefixed names are resolved against the prefixes stored in the files
+    let bgp = match parse_bgp(bgp_str) {
+        Ok(bgp) => bgp,
+        Err(_) => parse_bgp_with_namespaces(bgp_str, &read_namespaces(&conn, cottas_file_paths)?)?,
+    };
+    let (sql, variables) = translate_bgp(cottas_file_paths, &bgp);
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+
+    let mut solutions = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut bindings = HashMap::with_capacity(variables.len());
+        for (i, variable) in variables.iter().enumerate() {
+            let value: String = row.get(i)?;
+            bindings.insert(variable.clone(), parse_term(&value)?);
+        }
+        solutions.push(bindings);
+    }
+
+    Ok(solutions)
+}
+
+/// Searches for matches of a triple or quad pattern and returns them as `oxrdf` quads.
+///
+/// Matches of a triple pattern are returned in the default graph.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Quad>)` - The matching quads.
+pub fn search_quads_in_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<Quad>, CottasError> {
+    search_iter_in_duckdb(cottas_file_path, triple_pattern)?
+        .map(|row| {
+            let row = row?;
+            parse_quad(&row[0], &row[1], &row[2], row.get(3).map(String::as_str))
+        })
+        .collect()
+}
+
+/// How `cat` assigns a graph to the triples of triple-only inputs when they are concatenated
+/// with quad inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MixedGraphMode {
+    /// Triples stay in the default graph (a NULL `g` value).
+    #[default]
+    DefaultGraph,
+    /// Triples are placed in a named graph whose IRI is the `file://` URI of their input file.
+    FileGraph,
+}
+
+/// Builds the named graph IRI assigned to the triples of an input file.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the input file.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The `file://` IRI of the file in N-Triples syntax.
+fn file_graph_iri(cottas_file_path: &str) -> Result<String, CottasError> {
+    let path = fs::canonicalize(cottas_file_path)?;
+    let url = Url::from_file_path(&path).map_err(|()| {
+        CottasError::Unsupported(format!("file path {} as an IRI", path.display()))
+    })?;
+
+    // `Url` keeps some characters that are not allowed in IRIs
+    let iri: String = url
+        .as_str()
+        .chars()
+        .map(|c| match c {
+            '|' | '^' | '[' | ']' | '\\' => format!("%{:02X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect();
+    Ok(format!("<{}>", iri))
+}
+
+/// Concatenates multiple Parquet files into a single file, optionally removing the inputs.
+///
+/// If any input holds quads, the output keeps the graph column. Triples coming from
+/// triple-only inputs are assigned a graph according to `graph_mode`.
+///
+/// # Arguments
+///
+/// * `cottas_file_paths` - Slice of input file paths.
+/// * `cottas_cat_file_path` - Output file path.
+/// * `index` - Index string for ordering.
+/// * `remove_input_files` - If true, deletes input files after concatenation.
+/// * `graph_mode` - Graph assigned to triples of triple-only inputs when mixed with quads.
+/// * `options` - Options of the Parquet writer.
+///
+/// # Returns
+///
+/// * `Ok(())` on success.
+pub fn cat_duckdb(
+    cottas_file_paths: &[String],
+    cottas_cat_file_path: &str,
+    index: &str,
+    remove_input_files: bool,
+    graph_mode: MixedGraphMode,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    // Open DuckDB connection
+    let conn = connection_in_memory()?;
+
+    // Detect quad inputs
+    let mut input_has_graph = Vec::with_capacity(cottas_file_paths.len());
+    for file in cottas_file_paths {
+        input_has_graph.push(has_column(&conn, file, "g")?);
+    }
+    let quad_mode = input_has_graph.iter().any(|&g| g);
+
+    check_index(index, quad_mode)?;
+
+    // Build one SELECT per input file
+    let mut selects = Vec::with_capacity(cottas_file_paths.len());
+    for (file, &has_graph) in cottas_file_paths.iter().zip(&input_has_graph) {
+        let select = if !quad_mode {
+            format!("SELECT s, p, o FROM PARQUET_SCAN({})", sql_string(file))
+        } else if has_graph {
+            format!("SELECT s, p, o, g FROM PARQUET_SCAN({})", sql_string(file))
+        } else {
+            let graph = match graph_mode {
+                MixedGraphMode::DefaultGraph => "NULL".to_string(),
+                MixedGraphMode::FileGraph => sql_string(&file_graph_iri(file)?),
+            };
+            format!(
+                "SELECT s, p, o, {} AS g FROM PARQUET_SCAN({})",
+                graph,
+                sql_string(file)
+            )
+        };
+        selects.push(select);
+    }
+
+    let order_by = build_order_by(index, quad_mode)?;
+
+    // Build KV_METADATA, keeping the prefixes and base IRI of the inputs
+    let namespaces = read_namespaces(&conn, cottas_file_paths)?;
+    let kv_metadata = build_kv_metadata_inherited(index, quad_mode, &namespaces.to_kv_metadata());
+
+    // Build the COPY SQL query
+    let cat_query = format!(
+        "COPY (SELECT DISTINCT * FROM ({}) {}) TO {} ({}, {})",
+        selects.join(" UNION ALL "),
+        order_by,
+        sql_string(cottas_cat_file_path),
+        build_parquet_options(options)?,
+        kv_metadata
+    );
+
+    // Execute query
+    conn.execute(&cat_query, [])?;
+
+    // Optionally remove input files
+    if remove_input_files {
+        for file in cottas_file_paths {
+            fs::remove_file(file)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the difference between two Parquet files and writes the result to a new file.
+///
+/// # Arguments
+///
+/// * `cottas_file_1_path` - First input file path.
+/// * `cottas_file_2_path` - Second input file path.
+/// * `cottas_diff_file_path` - Output file path.
+/// * `index` - Index string for ordering.
+/// * `remove_input_files` - If true, deletes input files after diff.
+/// * `options` - Options of the Parquet writer.
+///
+/// # Returns
+///
+/// * `Ok(())` on success.
+pub fn diff_duckdb(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_diff_file_path: &str,
+    index: &str,
+    remove_input_files: bool,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    // Open DuckDB connection
+    let conn = connection_in_memory()?;
+
+    let quad_mode =
+        has_column(&conn, cottas_file_1_path, "g")? || has_column(&conn, cottas_file_2_path, "g")?;
+    check_index(index, quad_mode)?;
+
+    let diff_query = format!(
+        "SELECT DISTINCT * FROM ({}) EXCEPT SELECT * FROM ({})",
+        select_quads(&conn, cottas_file_1_path, quad_mode)?,
+        select_quads(&conn, cottas_file_2_path, quad_mode)?
+    );
+
+    // Keep the prefixes and base IRI of the first input
+    let namespaces = read_namespaces(&conn, &[cottas_file_1_path])?;
+    copy_to_cottas(
+        &conn,
+        &diff_query,
+        cottas_diff_file_path,
+        index,
+        quad_mode,
+        &namespaces.to_kv_metadata(),
+        options,
+    )?;
+
+    // Optionally remove input files
+    if remove_input_files {
+        fs::remove_file(cottas_file_1_path)?;
+        fs::remove_file(cottas_file_2_path)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the triples or quads present in both Parquet files to a new file.
+///
+/// # Arguments
+///
+/// * `cottas_file_1_path` - First input file path.
+/// * `cottas_file_2_path` - Second input file path.
+/// * `cottas_intersect_file_path` - Output file path.
+/// * `index` - Index string for ordering.
+/// * `remove_input_files` - If true, deletes input files after the intersection.
+/// * `options` - Options of the Parquet writer.
+///
+/// # Returns
+///
+/// * `Ok(())` on success.
+pub fn intersect_duckdb(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_intersect_file_path: &str,
+    index: &str,
+    remove_input_files: bool,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    set_operation_duckdb(
+        |first, second| format!("({first}) INTERSECT ({second})"),
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_intersect_file_path,
+        index,
+        remove_input_files,
+        options,
+    )
+}
+
+/// Writes the triples or quads present in exactly one of the Parquet files to a new file.
+///
+/// # Arguments
+///
+/// * `cottas_file_1_path` - First input file path.
+/// * `cottas_file_2_path` - Second input file path.
+/// * `cottas_symdiff_file_path` - Output file path.
+/// * `index` - Index string for ordering.
+/// * `remove_input_files` - If true, deletes input files after the symmetric difference.
+/// * `options` - Options of the Parquet writer.
+///
+/// # Returns
+///
+/// * `Ok(())` on success.
+pub fn symdiff_duckdb(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_symdiff_file_path: &str,
+    index: &str,
+    remove_input_files: bool,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    set_operation_duckdb(
+        |first, second| {
+            format!("(({first}) EXCEPT ({second})) UNION (({second}) EXCEPT ({first}))")
+        },
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_symdiff_file_path,
+        index,
+        remove_input_files,
+        options,
+    )
+}
 
-    // Create table
-    conn.execute("CREATE TABLE quads (s TEXT, p TEXT, o TEXT, g TEXT)", [])
-        .unwrap();
+/// Combines two Parquet files with a set operation and writes the result to a new file.
+///
+/// `operation` builds the combined query from the queries selecting the first and the second
+/// input. Triple-only inputs are read with a NULL graph when the other input holds quads.
+fn set_operation_duckdb(
+    operation: fn(&str, &str) -> String,
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_output_file_path: &str,
+    index: &str,
+    remove_input_files: bool,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    // Open DuckDB connection
+    let conn = connection_in_memory()?;
 
-    let mut stmt = conn
-        .prepare("INSERT INTO quads (s, p, o, g) VALUES (?, ?, ?, ?)")
-        .unwrap();
+    let quad_mode =
+        has_column(&conn, cottas_file_1_path, "g")? || has_column(&conn, cottas_file_2_path, "g")?;
+    check_index(index, quad_mode)?;
 
-    for (s, p, o, g) in quads {
-        let g_ref: &dyn ToSql = match g {
-            Some(val) => val,
-            None => &Option::<String>::None,
-        };
+    let query = operation(
+        &select_quads(&conn, cottas_file_1_path, quad_mode)?,
+        &select_quads(&conn, cottas_file_2_path, quad_mode)?,
+    );
+
+    let namespaces = read_namespaces(&conn, &[cottas_file_1_path, cottas_file_2_path])?;
+    copy_to_cottas(
+        &conn,
+        &query,
+        cottas_output_file_path,
+        index,
+        quad_mode,
+        &namespaces.to_kv_metadata(),
+        options,
+    )?;
 
-        stmt.execute([s as &dyn ToSql, p, o, g_ref]).unwrap();
+    // Optionally remove input files
+    if remove_input_files {
+        fs::remove_file(cottas_file_1_path)?;
+        fs::remove_file(cottas_file_2_path)?;
     }
 
-    conn
+    Ok(())
 }
 
-/// Creates a new in-memory DuckDB connection.
-///
-/// # Returns
+/// Builds a query selecting the triples or quads of a Parquet file.
 ///
-/// * `Connection` - An in-memory DuckDB connection.
-pub fn connection_in_memory() -> Connection {
-    Connection::open_in_memory().unwrap()
-}
-
-/// Checks if a Parquet file contains a specific column.
+/// In quad mode, triple-only files are read with a NULL graph, so files of both kinds can be
+/// combined with set operations.
 ///
 /// # Arguments
 ///
 /// * `conn` - The DuckDB connection.
 /// * `cottas_file_path` - Path to the Parquet file.
-/// * `column` - The column name to check.
+/// * `quad_mode` - If true, selects the graph column as well.
 ///
 /// # Returns
 ///
-/// * `Ok(true)` if the column exists, `Ok(false)` otherwise.
-pub fn has_column(
+/// * `Ok(String)` - The SELECT query.
+fn select_quads(
     conn: &Connection,
     cottas_file_path: &str,
-    column: &str,
-) -> Result<bool, Box<dyn Error>> {
-    let mut stmt = conn.prepare("SELECT name FROM PARQUET_SCHEMA(?)")?;
-    let mut rows = stmt.query([cottas_file_path])?;
-
-    while let Some(row) = rows.next()? {
-        let name: String = row.get(0)?;
-        if name == column {
-            return Ok(true);
-        }
-    }
+    quad_mode: bool,
+) -> Result<String, CottasError> {
+    let columns = if !quad_mode {
+        "s, p, o"
+    } else if has_column(conn, cottas_file_path, "g")? {
+        "s, p, o, g"
+    } else {
+        "s, p, o, NULL AS g"
+    };
 
-    Ok(false)
+    Ok(format!(
+        "SELECT {} FROM PARQUET_SCAN({})",
+        columns,
+        sql_string(cottas_file_path)
+    ))
 }
 
-/// Translates a triple or quad pattern into a DuckDB SQL query.
+/// Writes the result of a query to a Cottas file, sorted by the given index.
 ///
 /// # Arguments
 ///
-/// * `cottas_file_path` - Path to the Parquet file.
-/// * `triple_pattern` - The triple or quad pattern as a string.
+/// * `conn` - The DuckDB connection.
+/// * `query` - The SELECT query producing the triples or quads.
+/// * `cottas_file_path` - Output file path.
+/// * `index` - Index string for ordering.
+/// * `quad_mode` - If true, the query produces quads.
+/// * `inherited` - Key-value metadata to keep from the file the output derives from.
+/// * `options` - Options of the Parquet writer.
 ///
 /// # Returns
 ///
-/// * `String` - The generated SQL query.
-pub fn translate_triple_pattern(cottas_file_path: &str, triple_pattern: &str) -> String {
-    // Parse the triple pattern
-    let tp_tuple = parse_tp(triple_pattern);
-
-    let select_clause = if tp_tuple.len() == 3 {
-        "SELECT s, p, o"
-    } else if tp_tuple.len() == 4 {
-        "SELECT s, p, o, g"
-    } else {
-        panic!("Pattern must be a tuple of length 3 (triple) or 4 (quad)");
-    };
+/// * `Ok(())` on success.
+fn copy_to_cottas(
+    conn: &Connection,
+    query: &str,
+    cottas_file_path: &str,
+    index: &str,
+    quad_mode: bool,
+    inherited: &HashMap<String, String>,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    let order_by = build_order_by(index, quad_mode)?;
+    let kv_metadata = build_kv_metadata_inherited(index, quad_mode, inherited);
 
-    // Start building query
-    let mut query = format!(
-        "{} FROM PARQUET_SCAN('{}') WHERE ",
-        select_clause, cottas_file_path
+    let copy_query = format!(
+        "COPY (SELECT * FROM ({}) {}) TO {} ({}, {})",
+        query,
+        order_by,
+        sql_string(cottas_file_path),
+        build_parquet_options(options)?,
+        kv_metadata
     );
 
-    // Build WHERE clause - iterate over all positions
-    for i in 0..tp_tuple.len() {
-        let term = &tp_tuple[i];
-
-        // Only add condition if it's not a variable (doesn't start with ?)
-        if !term.starts_with('?') && !term.starts_with('$') {
-            // Escape single quotes to prevent SQL injection
-            let escaped_value = term.replace('\'', "''");
-            query.push_str(&format!("{}='{}' AND ", I_POS[i], escaped_value));
-        }
-    }
+    conn.execute(&copy_query, [])?;
+    Ok(())
+}
 
-    // Clean up trailing 'AND ' or 'WHERE '
-    if query.ends_with("AND ") {
-        query.truncate(query.len() - 4);
-    }
-    if query.ends_with("WHERE ") {
-        query.truncate(query.len() - 6);
-    }
+/// Destination of a changeset computed by [`changeset_duckdb`].
+#[derive(Debug, Clone, Copy)]
+pub enum ChangesetOutput<'a> {
+    /// Two Cottas files holding the added and the removed triples or quads.
+    Cottas {
+        /// Output file path for the added triples or quads.
+        added: &'a str,
+        /// Output file path for the removed triples or quads.
+        removed: &'a str,
+    },
+    /// An RDF Patch document at the given path.
+    RdfPatch(&'a str),
+    /// A SPARQL Update document at the given path.
+    SparqlUpdate(&'a str),
+}
 
-    query
+/// Number of added and removed triples or quads in a single graph.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphChanges {
+    /// Graph name, or `None` for the default graph.
+    pub graph: Option<String>,
+    /// Number of added triples or quads.
+    pub added: i64,
+    /// Number of removed triples or quads.
+    pub removed: i64,
 }
 
-/// Searches for matches of a triple or quad pattern in a Parquet file using DuckDB.
+/// Summary of a changeset between two Cottas files.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangesetSummary {
+    /// Number of added triples or quads.
+    pub added: i64,
+    /// Number of removed triples or quads.
+    pub removed: i64,
+    /// Counts per graph, if requested.
+    pub graphs: Option<Vec<GraphChanges>>,
+}
+
+/// Computes the triples or quads added and removed between two Cottas files.
 ///
 /// # Arguments
 ///
-/// * `cottas_file_path` - Path to the Parquet file.
-/// * `triple_pattern` - The triple or quad pattern as a string.
+/// * `cottas_old_file_path` - Path to the old version.
+/// * `cottas_new_file_path` - Path to the new version.
+/// * `output` - Where to write the added and removed sets.
+/// * `index` - Index string for ordering.
+/// * `per_graph` - If true, the summary also holds counts per graph.
+/// * `options` - Options of the Parquet writer, used when the sets are written as Cottas files.
 ///
 /// # Returns
 ///
-/// * `Ok(Vec<Vec<String>>)` - Query results as vectors of strings.
-pub fn search_in_duckdb(
-    cottas_file_path: &str,
-    triple_pattern: &str,
-) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
-    let conn = connection_in_memory();
-
-    // Translate pattern to SQL
-    let sql = translate_triple_pattern(cottas_file_path, triple_pattern);
-
-    // Prepare statement
-    let mut stmt = conn.prepare(&sql)?;
-
-    // Determine number of columns based on pattern
-    let tp_tuple = parse_tp(triple_pattern);
-    let column_count = tp_tuple.len();
+/// * `Ok(ChangesetSummary)` with the number of added and removed triples or quads.
+pub fn changeset_duckdb(
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    output: ChangesetOutput,
+    index: &str,
+    per_graph: bool,
+    options: &WriteOptions,
+) -> Result<ChangesetSummary, CottasError> {
+    // Open DuckDB connection
+    let conn = connection_in_memory()?;
+    let quad_mode = create_changeset_tables(
+        &conn,
+        cottas_old_file_path,
+        cottas_new_file_path,
+        Some(index),
+    )?;
 
-    // Execute query and collect results
-    let rows = stmt.query_map([], |row| {
-        let mut result = Vec::new();
-        for i in 0..column_count {
-            let val: String = row.get(i)?;
-            result.push(val);
+    match output {
+        ChangesetOutput::Cottas { added, removed } => {
+            let inherited = read_namespaces(&conn, &[cottas_new_file_path, cottas_old_file_path])?
+                .to_kv_metadata();
+            copy_to_cottas(
+                &conn,
+                "SELECT * FROM added",
+                added,
+                index,
+                quad_mode,
+                &inherited,
+                options,
+            )?;
+            copy_to_cottas(
+                &conn,
+                "SELECT * FROM removed",
+                removed,
+                index,
+                quad_mode,
+                &inherited,
+                options,
+            )?;
         }
-        Ok(result)
-    })?;
+        ChangesetOutput::RdfPatch(path) => {
+            write_changeset_to_path(&conn, ChangesetFormat::RdfPatch, quad_mode, path)?;
+        }
+        ChangesetOutput::SparqlUpdate(path) => {
+            write_changeset_to_path(&conn, ChangesetFormat::SparqlUpdate, quad_mode, path)?;
+        }
+    }
 
-    // Collect all rows
-    let results: Result<Vec<Vec<String>>, duckdb::Error> = rows.collect();
-    Ok(results?)
+    changeset_summary(&conn, quad_mode, per_graph)
 }
 
-/// Concatenates multiple Parquet files into a single file, optionally removing the inputs.
+/// Computes the changeset between two Parquet files and writes it as an RDF Patch or SPARQL
+/// Update document to any sink (e.g., standard output).
 ///
 /// # Arguments
 ///
-/// * `cottas_file_paths` - Slice of input file paths.
-/// * `cottas_cat_file_path` - Output file path.
-/// * `index` - Index string for ordering.
-/// * `remove_input_files` - If true, deletes input files after concatenation.
+/// * `cottas_old_file_path` - Path to the old version of the file.
+/// * `cottas_new_file_path` - Path to the new version of the file.
+/// * `format` - Format of the changeset document.
+/// * `writer` - The output sink.
+/// * `compression` - Optional compression of the output.
+/// * `per_graph` - If true, the summary includes counts per named graph.
 ///
 /// # Returns
 ///
-/// * `Ok(())` on success.
-pub fn cat_duckdb(
-    cottas_file_paths: &[String],
-    cottas_cat_file_path: &str,
-    index: &str,
-    remove_input_files: bool,
-) -> Result<(), Box<dyn Error>> {
-    if !is_valid_index(index) {
-        return Err(format!("Index `{}` is not valid.", index).into());
-    }
+/// * `Ok(ChangesetSummary)` - Number of added and removed triples or quads.
+pub fn changeset_writer_duckdb<W: Write>(
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    format: ChangesetFormat,
+    writer: W,
+    compression: Option<Compression>,
+    per_graph: bool,
+) -> Result<ChangesetSummary, CottasError> {
+    let conn = connection_in_memory()?;
+    let quad_mode =
+        create_changeset_tables(&conn, cottas_old_file_path, cottas_new_file_path, None)?;
 
-    // Join file paths for DuckDB PARQUET_SCAN
-    let parquet_files = cottas_file_paths
-        .iter()
-        .map(|p| format!("'{}'", p))
-        .collect::<Vec<_>>()
-        .join(", ");
+    let mut writer = CompressedWriter::new(writer, compression)?;
+    write_changeset_to_file(&conn, format, quad_mode, &mut writer)?;
+    writer.finish()?;
 
-    // Open DuckDB connection
-    let conn = connection_in_memory();
+    changeset_summary(&conn, quad_mode, per_graph)
+}
 
-    // Use build_order_by (Python-style: quad_mode = false)
-    let order_by = build_order_by(index, false);
+/// Creates the `added` and `removed` tables of the changeset between two Parquet files.
+///
+/// The `index` of Cottas outputs, if any, is checked against the data first.
+///
+/// # Returns
+///
+/// * `Ok(bool)` - Whether the tables hold quads.
+fn create_changeset_tables(
+    conn: &Connection,
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    index: Option<&str>,
+) -> Result<bool, CottasError> {
+    let quad_mode = has_column(conn, cottas_old_file_path, "g")?
+        || has_column(conn, cottas_new_file_path, "g")?;
+    if let Some(index) = index {
+        check_index(index, quad_mode)?;
+    }
 
-    // Build KV_METADATA index
-    let index_metadata = index.to_lowercase();
+    let old_select = select_quads(conn, cottas_old_file_path, quad_mode)?;
+    let new_select = select_quads(conn, cottas_new_file_path, quad_mode)?;
 
-    // Build the COPY SQL query
-    let cat_query = format!(
-        "COPY (SELECT DISTINCT s, p, o FROM PARQUET_SCAN([{}], union_by_name = true) {}) TO '{}' (
-            FORMAT PARQUET,
-            COMPRESSION ZSTD,
-            COMPRESSION_LEVEL 22,
-            PARQUET_VERSION v2,
-            KV_METADATA {{index: '{}'}}
-        )",
-        parquet_files, order_by, cottas_cat_file_path, index_metadata
-    );
+    // Materialize both directions once
+    conn.execute_batch(&format!(
+        "CREATE TEMP TABLE added AS SELECT DISTINCT * FROM ({new}) EXCEPT SELECT * FROM ({old});
+         CREATE TEMP TABLE removed AS SELECT DISTINCT * FROM ({old}) EXCEPT SELECT * FROM ({new});",
+        new = new_select,
+        old = old_select
+    ))?;
 
-    // Execute query
-    conn.execute(&cat_query, [])?;
+    Ok(quad_mode)
+}
 
-    // Optionally remove input files
-    if remove_input_files {
-        for file in cottas_file_paths {
-            fs::remove_file(file)?;
-        }
+/// Writes a changeset document to a file, compressed according to its extension.
+///
+/// The file is not created if a SPARQL Update would delete blank nodes.
+fn write_changeset_to_path(
+    conn: &Connection,
+    format: ChangesetFormat,
+    quad_mode: bool,
+    path: &str,
+) -> Result<(), CottasError> {
+    if format == ChangesetFormat::SparqlUpdate {
+        check_sparql_deletions(conn, quad_mode)?;
     }
 
+    let file = fs::File::create(path)?;
+    let mut writer = CompressedWriter::new(file, Compression::from_path(path))?;
+    write_checked_changeset(conn, format, quad_mode, &mut writer)?;
+    writer.finish()?;
     Ok(())
 }
 
-/// Computes the difference between two Parquet files and writes the result to a new file.
+/// Counts the rows of the `added` and `removed` tables of a changeset.
+fn changeset_summary(
+    conn: &Connection,
+    quad_mode: bool,
+    per_graph: bool,
+) -> Result<ChangesetSummary, CottasError> {
+    let added: i64 = conn.query_row("SELECT COUNT(*) FROM added", [], |row| row.get(0))?;
+    let removed: i64 = conn.query_row("SELECT COUNT(*) FROM removed", [], |row| row.get(0))?;
+
+    let graphs = if per_graph {
+        let graph_column = if quad_mode { "g" } else { "NULL::TEXT AS g" };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT g, SUM(a)::BIGINT, SUM(r)::BIGINT FROM (
+                SELECT {graph}, 1 AS a, 0 AS r FROM added
+                UNION ALL
+                SELECT {graph}, 0 AS a, 1 AS r FROM removed
+            ) GROUP BY g ORDER BY g NULLS FIRST",
+            graph = graph_column
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(GraphChanges {
+                graph: row.get(0)?,
+                added: row.get(1)?,
+                removed: row.get(2)?,
+            })
+        })?;
+        Some(rows.collect::<Result<Vec<_>, _>>()?)
+    } else {
+        None
+    };
+
+    Ok(ChangesetSummary {
+        added,
+        removed,
+        graphs,
+    })
+}
+
+/// Additions and deletions applied to a Cottas file by [`patch_duckdb`].
+#[derive(Debug, Clone, Copy)]
+pub enum PatchInput<'a> {
+    /// Two Cottas files holding the triples or quads to add and to remove.
+    Cottas {
+        /// Path to the Cottas file with the triples or quads to add.
+        added: &'a str,
+        /// Path to the Cottas file with the triples or quads to remove.
+        removed: &'a str,
+    },
+    /// An RDF Patch document at the given path.
+    RdfPatch(&'a str),
+}
+
+/// Loads the data rows of an RDF Patch document into the `patch_rows` table.
+///
+/// # Returns
+///
+/// * `Ok(true)` if at least one row belongs to a named graph, `Ok(false)` otherwise.
+fn load_rdf_patch(conn: &Connection, rdf_patch_path: &str) -> Result<bool, CottasError> {
+    conn.execute(
+        "CREATE TEMP TABLE patch_rows (seq BIGINT, op TEXT, s TEXT, p TEXT, o TEXT, g TEXT)",
+        [],
+    )?;
+
+    let mut appender = conn.appender("patch_rows")?;
+    let mut quad_mode = false;
+
+    for (seq, row) in parse_rdf_patch(rdf_patch_path)?.enumerate() {
+        let (operation, (s, p, o, g)) = row?;
+        quad_mode |= g.is_some();
+
+        let op = match operation {
+            PatchOperation::Add => "A",
+            PatchOperation::Delete => "D",
+        };
+        appender.append_row(params![seq as i64, op, s, p, o, g])?;
+    }
+    appender.flush()?;
+
+    Ok(quad_mode)
+}
+
+/// Applies additions and deletions to a Cottas file and writes the new version.
+///
+/// The output is sorted by the index of the base file and keeps its key-value metadata.
+/// For RDF Patch inputs, the last row for a given triple or quad wins.
 ///
 /// # Arguments
 ///
-/// * `cottas_file_1_path` - First input file path.
-/// * `cottas_file_2_path` - Second input file path.
-/// * `cottas_diff_file_path` - Output file path.
-/// * `index` - Index string for ordering.
-/// * `remove_input_files` - If true, deletes input files after diff.
+/// * `cottas_base_file_path` - Path to the base Cottas file.
+/// * `input` - The additions and deletions to apply.
+/// * `cottas_patched_file_path` - Output file path.
+/// * `options` - Options of the Parquet writer.
 ///
 /// # Returns
 ///
 /// * `Ok(())` on success.
-pub fn diff_duckdb(
-    cottas_file_1_path: &str,
-    cottas_file_2_path: &str,
-    cottas_diff_file_path: &str,
-    index: &str,
-    remove_input_files: bool,
-) -> Result<(), Box<dyn Error>> {
-    if !is_valid_index(index) {
-        return Err(format!("Index `{}` is not valid.", index).into());
-    }
-
+pub fn patch_duckdb(
+    cottas_base_file_path: &str,
+    input: PatchInput,
+    cottas_patched_file_path: &str,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
     // Open DuckDB connection
-    let conn = connection_in_memory();
+    let conn = connection_in_memory()?;
 
-    // Use build_order_by (Python-style: quad_mode = false)
-    let order_by = build_order_by(index, false);
+    let base_metadata = read_kv_metadata(&conn, cottas_base_file_path)?;
+    let index = base_metadata
+        .get("index")
+        .filter(|index| is_valid_index(index))
+        .cloned()
+        .unwrap_or_else(|| "spo".to_string());
+    let base_has_graph = has_column(&conn, cottas_base_file_path, "g")?;
 
-    // Build KV_METADATA index
-    let index_metadata = index.to_lowercase();
+    let (quad_mode, additions, deletions) = match input {
+        PatchInput::Cottas { added, removed } => {
+            let quad_mode = base_has_graph
+                || has_column(&conn, added, "g")?
+                || has_column(&conn, removed, "g")?;
+            (
+                quad_mode,
+                select_quads(&conn, added, quad_mode)?,
+                select_quads(&conn, removed, quad_mode)?,
+            )
+        }
+        PatchInput::RdfPatch(path) => {
+            let quad_mode = load_rdf_patch(&conn, path)? || base_has_graph;
+            let columns = if quad_mode { "s, p, o, g" } else { "s, p, o" };
+            let last_op =
+                "SELECT s, p, o, g, ARG_MAX(op, seq) AS op FROM patch_rows GROUP BY s, p, o, g";
+            (
+                quad_mode,
+                format!("SELECT {} FROM ({}) WHERE op = 'A'", columns, last_op),
+                format!("SELECT {} FROM ({}) WHERE op = 'D'", columns, last_op),
+            )
+        }
+    };
+    check_index(&index, quad_mode)?;
 
-    // Build the COPY SQL query
-    let diff_query = format!(
-        "COPY (SELECT * FROM (SELECT DISTINCT * FROM PARQUET_SCAN('{}') EXCEPT SELECT * FROM PARQUET_SCAN('{}')) {}) TO '{}' (
-            FORMAT PARQUET,
-            COMPRESSION ZSTD,
-            COMPRESSION_LEVEL 22,
-            PARQUET_VERSION v2,
-            KV_METADATA {{index: '{}'}}
-        )",
-        cottas_file_1_path,
-        cottas_file_2_path,
-        order_by,
-        cottas_diff_file_path,
-        index_metadata
+    let base = select_quads(&conn, cottas_base_file_path, quad_mode)?;
+    let patched_query = format!(
+        "SELECT * FROM (({}) EXCEPT ({})) UNION ({})",
+        base, deletions, additions
     );
 
-    // Execute query
-    conn.execute(&diff_query, [])?;
-
-    // Optionally remove input files
-    if remove_input_files {
-        fs::remove_file(cottas_file_1_path)?;
-        fs::remove_file(cottas_file_2_path)?;
-    }
-
-    Ok(())
+    copy_to_cottas(
+        &conn,
+        &patched_query,
+        cottas_patched_file_path,
+        &index,
+        quad_mode,
+        &base_metadata,
+        options,
+    )
 }
 
 /// Verifies that a Parquet file has the required columns for a Cottas file.
@@ -305,12 +1499,20 @@ pub fn diff_duckdb(
 /// # Returns
 ///
 /// * `Ok(true)` if valid, `Ok(false)` otherwise.
-pub fn verify_duckdb(cottas_file_path: &str) -> Result<bool, Box<dyn Error>> {
-    let conn = connection_in_memory();
+pub fn verify_duckdb(cottas_file_path: &str) -> Result<bool, CottasError> {
+    let conn = connection_in_memory()?;
+    let cottas_columns = parquet_columns(&conn, cottas_file_path)?;
+    Ok(has_cottas_columns(&cottas_columns))
+}
 
+/// Reads the column names of a Parquet file.
+pub(crate) fn parquet_columns(
+    conn: &Connection,
+    cottas_file_path: &str,
+) -> Result<HashSet<String>, CottasError> {
     let verify_query = format!(
-        "DESCRIBE SELECT * FROM PARQUET_SCAN('{}') LIMIT 1",
-        cottas_file_path
+        "DESCRIBE SELECT * FROM PARQUET_SCAN({}) LIMIT 1",
+        sql_string(cottas_file_path)
     );
 
     let mut stmt = conn.prepare(&verify_query)?;
@@ -324,9 +1526,14 @@ pub fn verify_duckdb(cottas_file_path: &str) -> Result<bool, Box<dyn Error>> {
         cottas_columns.insert(row?);
     }
 
+    Ok(cottas_columns)
+}
+
+/// Checks that the columns of a Parquet file are `s`, `p`, `o`, and optionally `g`.
+pub(crate) fn has_cottas_columns(cottas_columns: &HashSet<String>) -> bool {
     for pos in ['s', 'p', 'o'] {
         if !cottas_columns.contains(&pos.to_string()) {
-            return Ok(false);
+            return false;
         }
     }
 
@@ -335,9 +1542,32 @@ pub fn verify_duckdb(cottas_file_path: &str) -> Result<bool, Box<dyn Error>> {
         .map(|&c| c.to_string())
         .collect();
 
-    let is_valid = cottas_columns.is_subset(&valid_columns);
+    cottas_columns.is_subset(&valid_columns)
+}
+
+/// Checks that a file can be read as a Cottas file.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_path` - Path to the Parquet file.
+///
+/// # Errors
+///
+/// Returns [`CottasError::InvalidCottasFile`] if the file is not a Parquet file or does not
+/// have the columns of a Cottas file.
+pub fn check_cottas_file(conn: &Connection, cottas_file_path: &str) -> Result<(), CottasError> {
+    let cottas_columns = parquet_columns(conn, cottas_file_path)
+        .map_err(|e| CottasError::invalid_cottas_file(cottas_file_path, e.to_string()))?;
+
+    if !has_cottas_columns(&cottas_columns) {
+        return Err(CottasError::invalid_cottas_file(
+            cottas_file_path,
+            "expected the columns `s`, `p`, `o`, and optionally `g`",
+        ));
+    }
 
-    Ok(is_valid)
+    Ok(())
 }
 
 /// Metadata about a Cottas Parquet file.
@@ -345,6 +1575,10 @@ pub fn verify_duckdb(cottas_file_path: &str) -> Result<bool, Box<dyn Error>> {
 pub struct CottasInfo {
     /// Index string (e.g., "spo").
     pub index: String,
+    /// COTTAS format version the file was written with.
+    pub format_version: String,
+    /// Name and version of the library that created the file.
+    pub library_version: String,
     /// Number of triples.
     pub triples: i64,
     /// Number of row groups.
@@ -355,7 +1589,7 @@ pub struct CottasInfo {
     pub distinct_subjects: i64,
     /// Number of distinct objects.
     pub distinct_objects: i64,
-    /// File creation or modification timestamp (RFC3339).
+    /// Creation timestamp (RFC3339), falling back to the file timestamp for files without metadata.
     pub issued: String,
     /// File size in megabytes.
     pub size_mb: f64,
@@ -363,6 +1597,42 @@ pub struct CottasInfo {
     pub compression: String,
     /// True if file contains quads (has a graph column).
     pub quads: bool,
+    /// Number of row groups that a search for the pattern given to [`info_with_pattern_duckdb`]
+    /// reads, or `None` if no pattern was given.
+    pub touched_row_groups: Option<i64>,
+}
+
+/// Reads the key-value metadata of a Parquet file.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_path` - Path to the Parquet file.
+///
+/// # Returns
+///
+/// * `Ok(HashMap<String, String>)` mapping each metadata key to its value.
+pub fn read_kv_metadata(
+    conn: &Connection,
+    cottas_file_path: &str,
+) -> Result<HashMap<String, String>, CottasError> {
+    let mut stmt = conn.prepare("SELECT key, value FROM PARQUET_KV_METADATA(?)")?;
+    let rows = stmt.query_map([cottas_file_path], |row| {
+        let key: Vec<u8> = row.get(0)?;
+        let value: Vec<u8> = row.get(1)?;
+        Ok((
+            String::from_utf8_lossy(&key).to_string(),
+            String::from_utf8_lossy(&value).to_string(),
+        ))
+    })?;
+
+    let mut kv_metadata = HashMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        kv_metadata.insert(key, value);
+    }
+
+    Ok(kv_metadata)
 }
 
 /// Extracts metadata and statistics from a Cottas Parquet file.
@@ -375,52 +1645,80 @@ pub struct CottasInfo {
 ///
 /// * `Ok(CottasInfo)` with file metadata and statistics.
 ///
-pub fn info_duckdb(cottas_file_path: &str) -> Result<CottasInfo, Box<dyn Error>> {
-    let conn = connection_in_memory();
+pub fn info_duckdb(cottas_file_path: &str) -> Result<CottasInfo, CottasError> {
+    let conn = connection_in_memory()?;
+    info_with_connection(&conn, cottas_file_path, None)
+}
+
+/// Extracts metadata and statistics from a Cottas Parquet file, including the number of row
+/// groups that a search for a triple or quad pattern reads.
+///
+/// Row groups are skipped as in [`prune_row_groups`], using only the Parquet metadata.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `triple_pattern` - The triple or quad pattern as a string.
+///
+/// # Returns
+///
+/// * `Ok(CottasInfo)` with file metadata and statistics.
+pub fn info_with_pattern_duckdb(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<CottasInfo, CottasError> {
+    let conn = connection_in_memory()?;
+    let tp_tuple = parse_file_pattern(&conn, cottas_file_path, triple_pattern)?;
+    info_with_connection(&conn, cottas_file_path, Some(&tp_tuple))
+}
 
+/// Extracts metadata and statistics from a Cottas Parquet file using an existing connection.
+///
+/// The row groups touched by `tp_tuple` are counted if a parsed pattern is given.
+pub(crate) fn info_with_connection(
+    conn: &Connection,
+    cottas_file_path: &str,
+    tp_tuple: Option<&[String]>,
+) -> Result<CottasInfo, CottasError> {
     // Get file metadata
     let metadata = fs::metadata(cottas_file_path)?;
-    let ctime = metadata.created().or_else(|_| metadata.modified())?;
-    let cottas_issued: DateTime<Utc> = ctime.into();
     let size_mb = metadata.len() as f64 / 1_000_000.0;
+    check_cottas_file(conn, cottas_file_path)?;
+    let kv_metadata = read_kv_metadata(conn, cottas_file_path)?;
 
     // Build queries
-    let kv_query = format!(
-        "SELECT * FROM PARQUET_KV_METADATA('{}') WHERE key='index'",
-        cottas_file_path
-    );
+    let file = sql_string(cottas_file_path);
     let row_query = format!(
-        "SELECT num_rows AS triples, num_row_groups AS triples_groups FROM PARQUET_FILE_METADATA('{}')",
-        cottas_file_path
-    );
-    let properties_query = format!(
-        "SELECT COUNT(DISTINCT p) FROM PARQUET_SCAN('{}')",
-        cottas_file_path
-    );
-    let distinct_subjects_query = format!(
-        "SELECT COUNT(DISTINCT s) FROM PARQUET_SCAN('{}')",
-        cottas_file_path
-    );
-    let distinct_objects_query = format!(
-        "SELECT COUNT(DISTINCT o) FROM PARQUET_SCAN('{}')",
-        cottas_file_path
-    );
-    let schema_query = format!(
-        "DESCRIBE SELECT * FROM PARQUET_SCAN('{}') LIMIT 1",
-        cottas_file_path
-    );
-    let compression_query = format!(
-        "SELECT compression FROM PARQUET_METADATA('{}')",
-        cottas_file_path
+        "SELECT num_rows AS triples, num_row_groups AS triples_groups FROM PARQUET_FILE_METADATA({})",
+        file
     );
+    let properties_query = format!("SELECT COUNT(DISTINCT p) FROM PARQUET_SCAN({})", file);
+    let distinct_subjects_query = format!("SELECT COUNT(DISTINCT s) FROM PARQUET_SCAN({})", file);
+    let distinct_objects_query = format!("SELECT COUNT(DISTINCT o) FROM PARQUET_SCAN({})", file);
+    let schema_query = format!("DESCRIBE SELECT * FROM PARQUET_SCAN({}) LIMIT 1", file);
+    let compression_query = format!("SELECT compression FROM PARQUET_METADATA({})", file);
 
-    let index: String = conn
-        .query_row(&kv_query, [], |row| {
-            let value: Vec<u8> = row.get(2)?;
-            Ok(String::from_utf8_lossy(&value).to_string())
-        })
-        .optional()?
+    let index = kv_metadata
+        .get("index")
+        .cloned()
+        .unwrap_or_else(|| "unknown".to_string());
+    let format_version = kv_metadata
+        .get("format_version")
+        .cloned()
         .unwrap_or_else(|| "unknown".to_string());
+    let library_version = kv_metadata
+        .get("library_version")
+        .cloned()
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let issued = match kv_metadata.get("issued") {
+        Some(issued) => issued.clone(),
+        None => {
+            let ctime = metadata.created().or_else(|_| metadata.modified())?;
+            let cottas_issued: DateTime<Utc> = ctime.into();
+            cottas_issued.to_rfc3339()
+        }
+    };
 
     let (triples, triples_groups): (i64, i64) = conn
         .query_row(&row_query, [], |row| Ok((row.get(0)?, row.get(1)?)))
@@ -456,18 +1754,33 @@ pub fn info_duckdb(cottas_file_path: &str) -> Result<CottasInfo, Box<dyn Error>>
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
-    let quads = column_names.contains(&"g".to_string());
+    let quads = match kv_metadata.get("quads") {
+        Some(quads) => quads == "true",
+        None => column_names.contains(&"g".to_string()),
+    };
+
+    // Patterns without constants touch every row group
+    let touched_row_groups = match tp_tuple {
+        Some(tp_tuple) if has_constants(tp_tuple) => {
+            Some(prune_row_groups(conn, cottas_file_path, tp_tuple)?.len() as i64)
+        }
+        Some(_) => Some(triples_groups),
+        None => None,
+    };
 
     Ok(CottasInfo {
         index,
+        format_version,
+        library_version,
         triples,
         triples_groups,
         properties,
         distinct_subjects,
         distinct_objects,
-        issued: cottas_issued.to_rfc3339(),
+        issued,
         size_mb,
         compression,
         quads,
+        touched_row_groups,
     })
 }
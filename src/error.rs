@@ -0,0 +1,208 @@
+//! Error type returned by every fallible function of the library.
+
+use oxrdf::{IriParseError, LanguageTagParseError, TermParseError, VariableNameParseError};
+use oxrdfio::RdfParseError;
+use spargebra::SparqlSyntaxError;
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors of the COTTAS library.
+#[derive(Debug)]
+pub enum CottasError {
+    /// Reading or writing a file or stream failed.
+    Io(io::Error),
+    /// An RDF document is not valid, with the 1-based position of the error if known.
+    Syntax {
+        /// Description of the error.
+        message: String,
+        /// Line of the error, starting at 1.
+        line: Option<u64>,
+        /// Column of the error, starting at 1.
+        column: Option<u64>,
+    },
+    /// An index is not valid, or not applicable to the data (e.g., `gspo` for triples).
+    InvalidIndex {
+        /// The index string.
+        index: String,
+        /// Why the index was rejected.
+        message: String,
+    },
+    /// A triple pattern or basic graph pattern is malformed.
+    InvalidPattern {
+        /// The pattern string.
+        pattern: String,
+        /// Why the pattern was rejected.
+        message: String,
+    },
+    /// An RDF term (e.g., an IRI or a language tag) is not valid.
+    InvalidTerm(String),
+    /// A SPARQL query is not valid.
+    InvalidQuery(String),
+    /// A format, codec, or query feature is not supported.
+    Unsupported(String),
+    /// DuckDB failed to run a query.
+    DuckDb(duckdb::Error),
+    /// A file is not a valid Cottas file.
+    InvalidCottasFile {
+        /// Path to the file.
+        path: String,
+        /// Why the file was rejected.
+        message: String,
+    },
+    /// A background thread running a query panicked, with the panic message.
+    WorkerPanicked(String),
+}
+
+impl CottasError {
+    /// Builds an [`CottasError::InvalidIndex`] error.
+    pub(crate) fn invalid_index(index: &str, message: impl Into<String>) -> Self {
+        Self::InvalidIndex {
+            index: index.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Builds an [`CottasError::InvalidPattern`] error.
+    pub(crate) fn invalid_pattern(pattern: &str, message: impl Into<String>) -> Self {
+        Self::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Builds an [`CottasError::InvalidCottasFile`] error.
+    pub(crate) fn invalid_cottas_file(path: &str, message: impl Into<String>) -> Self {
+        Self::InvalidCottasFile {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Builds a [`CottasError::WorkerPanicked`] error from the payload of a panic.
+    pub(crate) fn worker_panicked(payload: &(dyn Any + Send)) -> Self {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => payload
+                .downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_else(|| "unknown panic".to_string()),
+        };
+        Self::WorkerPanicked(message)
+    }
+
+    /// Builds a [`CottasError::Syntax`] error at a known line.
+    pub(crate) fn syntax_at_line(message: impl Into<String>, line: u64) -> Self {
+        Self::Syntax {
+            message: message.into(),
+            line: Some(line),
+            column: None,
+        }
+    }
+}
+
+impl fmt::Display for CottasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Syntax {
+                message,
+                line: Some(line),
+                column: Some(column),
+            } => write!(
+                f,
+                "Syntax error at line {}, column {}: {}",
+                line, column, message
+            ),
+            Self::Syntax {
+                message,
+                line: Some(line),
+                column: None,
+            } => write!(f, "Syntax error at line {}: {}", line, message),
+            Self::Syntax { message, .. } => write!(f, "Syntax error: {}", message),
+            Self::InvalidIndex { index, message } => {
+                write!(f, "Index `{}` is not valid: {}", index, message)
+            }
+            Self::InvalidPattern { pattern, message } => {
+                write!(f, "Invalid pattern `{}`: {}", pattern, message)
+            }
+            Self::InvalidTerm(message) => write!(f, "Invalid RDF term: {}", message),
+            Self::InvalidQuery(message) => write!(f, "Invalid SPARQL query: {}", message),
+            Self::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            Self::DuckDb(e) => write!(f, "DuckDB error: {}", e),
+            Self::InvalidCottasFile { path, message } => {
+                write!(f, "Invalid Cottas file `{}`: {}", path, message)
+            }
+            Self::WorkerPanicked(message) => write!(f, "Query thread panicked: {}", message),
+        }
+    }
+}
+
+impl Error for CottasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::DuckDb(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CottasError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<duckdb::Error> for CottasError {
+    fn from(e: duckdb::Error) -> Self {
+        Self::DuckDb(e)
+    }
+}
+
+impl From<RdfParseError> for CottasError {
+    fn from(e: RdfParseError) -> Self {
+        match e {
+            RdfParseError::Io(e) => Self::Io(e),
+            RdfParseError::Syntax(e) => {
+                let start = e.location().map(|location| location.start);
+                Self::Syntax {
+                    message: e.to_string(),
+                    line: start.map(|position| position.line + 1),
+                    column: start.map(|position| position.column + 1),
+                }
+            }
+        }
+    }
+}
+
+impl From<SparqlSyntaxError> for CottasError {
+    fn from(e: SparqlSyntaxError) -> Self {
+        Self::InvalidQuery(e.to_string())
+    }
+}
+
+impl From<IriParseError> for CottasError {
+    fn from(e: IriParseError) -> Self {
+        Self::InvalidTerm(e.to_string())
+    }
+}
+
+impl From<LanguageTagParseError> for CottasError {
+    fn from(e: LanguageTagParseError) -> Self {
+        Self::InvalidTerm(e.to_string())
+    }
+}
+
+impl From<TermParseError> for CottasError {
+    fn from(e: TermParseError) -> Self {
+        Self::InvalidTerm(e.to_string())
+    }
+}
+
+impl From<VariableNameParseError> for CottasError {
+    fn from(e: VariableNameParseError) -> Self {
+        Self::InvalidQuery(e.to_string())
+    }
+}
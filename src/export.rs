@@ -1,10 +1,16 @@
 //! Export utilities for writing query results to Cottas and files.
 
-use crate::utils::build_order_by;
+use crate::error::CottasError;
+use crate::parser::{parse_quad, Namespaces};
+use crate::utils::{
+    build_kv_metadata_inherited, build_order_by, build_parquet_options, check_index, sql_string,
+    Compression, WriteOptions,
+};
 use duckdb::Connection;
-use std::error::Error;
-use std::fs::File;
-use std::io::Write;
+use oxrdfio::{RdfFormat, RdfSerializer};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufWriter, Write};
 
 /// Exports query results from DuckDB to a Cottas (Parquet) file.
 ///
@@ -14,78 +20,346 @@ use std::io::Write;
 /// * `index` - The index string used for ordering.
 /// * `path` - The output file path.
 /// * `quad_mode` - If true, exports quads; otherwise, exports triples.
+/// * `metadata` - Additional key-value metadata (e.g., the entries of [`Namespaces::to_kv_metadata`]).
+/// * `options` - Options of the Parquet writer.
+///
+/// # Errors
+///
+/// Returns an error if the index or the writer options are not valid, or if the export fails.
 pub fn export_to_cottas(
     conn: &Connection,
     index: &str,
     path: &str,
     quad_mode: bool,
-) -> Result<(), Box<dyn Error>> {
+    metadata: &HashMap<String, String>,
+    options: &WriteOptions,
+) -> Result<(), CottasError> {
+    check_index(index, quad_mode)?;
+
     let select = if quad_mode {
         "SELECT DISTINCT s, p, o, g FROM quads"
     } else {
         "SELECT DISTINCT s, p, o FROM quads"
     };
 
-    let order_by = build_order_by(index, quad_mode);
+    let order_by = build_order_by(index, quad_mode)?;
+
+    let kv_metadata = build_kv_metadata_inherited(index, quad_mode, metadata);
 
     let query = format!(
-        "COPY ({} {}) TO '{}' (FORMAT PARQUET, COMPRESSION ZSTD, COMPRESSION_LEVEL 22, PARQUET_VERSION 'V2')",
-        select, order_by, path
+        "COPY ({} {}) TO {} ({}, {})",
+        select,
+        order_by,
+        sql_string(path),
+        build_parquet_options(options)?,
+        kv_metadata
     );
 
-    conn.execute(query.as_str(), [])?; // <-- was .unwrap()
+    conn.execute(query.as_str(), [])?;
     Ok(())
 }
 
-/// Writes quads or triples from a Cottas (Parquet) file to a text file.
+/// A writer that optionally compresses its output.
+///
+/// [`CompressedWriter::finish`] must be called to write the end of the compressed stream.
+pub enum CompressedWriter<W: Write> {
+    /// Uncompressed output.
+    Plain(W),
+    /// Gzip-compressed output.
+    Gzip(flate2::write::GzEncoder<W>),
+    /// Zstandard-compressed output.
+    Zstd(zstd::Encoder<'static, W>),
+    /// Bzip2-compressed output.
+    Bzip2(bzip2::write::BzEncoder<W>),
+    /// XZ-compressed output.
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Wraps a writer with the given compression.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The output sink.
+    /// * `compression` - The compression codec, or `None` for uncompressed output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoder cannot be created.
+    pub fn new(writer: W, compression: Option<Compression>) -> io::Result<Self> {
+        Ok(match compression {
+            None => Self::Plain(writer),
+            Some(Compression::Gzip) => Self::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Some(Compression::Zstd) => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+            Some(Compression::Bzip2) => Self::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            )),
+            Some(Compression::Xz) => Self::Xz(xz2::write::XzEncoder::new(writer, 6)),
+        })
+    }
+
+    /// Completes the compressed stream and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            Self::Plain(writer) => writer,
+            Self::Gzip(encoder) => encoder.finish()?,
+            Self::Zstd(encoder) => encoder.finish()?,
+            Self::Bzip2(encoder) => encoder.finish()?,
+            Self::Xz(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+            Self::Bzip2(encoder) => encoder.write(buf),
+            Self::Xz(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+            Self::Bzip2(encoder) => encoder.flush(),
+            Self::Xz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writes quads or triples from a Cottas (Parquet) file as RDF to a writer.
 ///
 /// # Arguments
 ///
 /// * `conn` - The DuckDB connection.
 /// * `cottas_file_path` - Path to the Cottas (Parquet) file.
 /// * `has_named_graph` - If true, expects quads; otherwise, triples.
-/// * `file` - Mutable reference to the output file.
+/// * `format` - The RDF serialization format.
+/// * `namespaces` - Prefixes and base IRI used by formats that support them (e.g., Turtle).
+/// * `writer` - The output sink (e.g., a file or standard output).
 ///
 /// # Errors
 ///
-/// Returns an error if reading or writing fails.
-pub fn write_quads_to_file(
+/// Returns an error if reading or writing fails, or if a quad in a named graph is written in a
+/// format without named graphs (e.g., Turtle).
+pub fn write_quads_to_file<W: Write>(
     conn: &Connection,
     cottas_file_path: &str,
     has_named_graph: bool,
-    file: &mut File,
-) -> Result<(), Box<dyn Error>> {
+    format: RdfFormat,
+    namespaces: &Namespaces,
+    writer: W,
+) -> Result<(), CottasError> {
     let select = if has_named_graph {
         "s, p, o, g"
     } else {
         "s, p, o"
     };
     let query = format!(
-        "SELECT {} FROM PARQUET_SCAN('{}')",
-        select, cottas_file_path
+        "SELECT {} FROM PARQUET_SCAN({})",
+        select,
+        sql_string(cottas_file_path)
     );
 
     let mut stmt = conn.prepare(&query)?;
     let mut rows = stmt.query([])?;
+    let mut serializer = RdfSerializer::from_format(format);
+    if let Some(base_iri) = &namespaces.base_iri {
+        serializer = serializer.with_base_iri(base_iri.as_str())?;
+    }
+    for (name, iri) in &namespaces.prefixes {
+        serializer = serializer.with_prefix(name.as_str(), iri.as_str())?;
+    }
+    let mut serializer = serializer.for_writer(BufWriter::new(writer));
 
     while let Some(row) = rows.next()? {
-        let line = if has_named_graph {
-            format!(
-                "{} {} {} {} .\n",
-                row.get::<_, String>(0).unwrap_or_default(),
-                row.get::<_, String>(1).unwrap_or_default(),
-                row.get::<_, String>(2).unwrap_or_default(),
-                row.get::<_, String>(3).unwrap_or_default(),
-            )
-        } else {
-            format!(
-                "{} {} {} .\n",
-                row.get::<_, String>(0).unwrap_or_default(),
-                row.get::<_, String>(1).unwrap_or_default(),
-                row.get::<_, String>(2).unwrap_or_default(),
-            )
-        };
-        file.write_all(line.as_bytes())?;
+        let g: Option<String> = if has_named_graph { row.get(3)? } else { None };
+        let quad = parse_quad(
+            &row.get::<_, String>(0)?,
+            &row.get::<_, String>(1)?,
+            &row.get::<_, String>(2)?,
+            g.as_deref(),
+        )?;
+        serializer.serialize_quad(&quad)?;
+    }
+
+    serializer.finish()?.flush()?;
+    Ok(())
+}
+
+/// Text formats for writing a changeset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangesetFormat {
+    /// RDF Patch, with one `D` or `A` row per removed or added triple or quad.
+    RdfPatch,
+    /// SPARQL Update, with a `DELETE DATA` and an `INSERT DATA` operation.
+    SparqlUpdate,
+}
+
+/// Writes the `added` and `removed` tables of a DuckDB connection as a changeset document.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection holding the `added` and `removed` tables.
+/// * `format` - The changeset format.
+/// * `has_named_graph` - If true, the tables hold quads; otherwise, triples.
+/// * `writer` - The output sink (e.g., a file, or standard output).
+///
+/// # Errors
+///
+/// Returns [`CottasError::Unsupported`] if a SPARQL Update would delete blank nodes, which
+/// `DELETE DATA` does not allow, or an error if reading or writing fails. Nothing is written
+/// in the former case.
+pub fn write_changeset_to_file<W: Write>(
+    conn: &Connection,
+    format: ChangesetFormat,
+    has_named_graph: bool,
+    writer: W,
+) -> Result<(), CottasError> {
+    if format == ChangesetFormat::SparqlUpdate {
+        check_sparql_deletions(conn, has_named_graph)?;
+    }
+
+    write_checked_changeset(conn, format, has_named_graph, writer)
+}
+
+/// Writes the `added` and `removed` tables of a DuckDB connection as a changeset document,
+/// like [`write_changeset_to_file`], without checking the deletions of a SPARQL Update.
+///
+/// Callers must run [`check_sparql_deletions`] first when writing a SPARQL Update.
+pub(crate) fn write_checked_changeset<W: Write>(
+    conn: &Connection,
+    format: ChangesetFormat,
+    has_named_graph: bool,
+    writer: W,
+) -> Result<(), CottasError> {
+    let mut writer = BufWriter::new(writer);
+
+    match format {
+        ChangesetFormat::RdfPatch => {
+            writeln!(writer, "TX .")?;
+            write_changeset_rows(conn, "removed", has_named_graph, &mut writer, |t, g| {
+                rdf_patch_row("D", t, g)
+            })?;
+            write_changeset_rows(conn, "added", has_named_graph, &mut writer, |t, g| {
+                rdf_patch_row("A", t, g)
+            })?;
+            writeln!(writer, "TC .")?;
+        }
+        ChangesetFormat::SparqlUpdate => {
+            writeln!(writer, "DELETE DATA {{")?;
+            write_changeset_rows(
+                conn,
+                "removed",
+                has_named_graph,
+                &mut writer,
+                sparql_quad_data,
+            )?;
+            writeln!(writer, "}} ;")?;
+            writeln!(writer, "INSERT DATA {{")?;
+            write_changeset_rows(
+                conn,
+                "added",
+                has_named_graph,
+                &mut writer,
+                sparql_quad_data,
+            )?;
+            writeln!(writer, "}}")?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Checks that the `removed` table of a changeset can be written as SPARQL `DELETE DATA`.
+///
+/// # Errors
+///
+/// Returns [`CottasError::Unsupported`] if a removed triple or quad holds a blank node.
+pub(crate) fn check_sparql_deletions(
+    conn: &Connection,
+    has_named_graph: bool,
+) -> Result<(), CottasError> {
+    let mut condition = "starts_with(s, '_:') OR starts_with(o, '_:')".to_string();
+    if has_named_graph {
+        condition.push_str(" OR starts_with(g, '_:')");
+    }
+
+    let blank_nodes: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM removed WHERE {}", condition),
+        [],
+        |row| row.get(0),
+    )?;
+    if blank_nodes > 0 {
+        return Err(CottasError::Unsupported(
+            "blank nodes in SPARQL Update DELETE DATA".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Formats a triple and its optional graph name as an RDF Patch row with the given operation.
+fn rdf_patch_row(operation: &str, triple: &str, graph: Option<&str>) -> String {
+    match graph {
+        Some(graph) => format!("{} {} {} .\n", operation, triple, graph),
+        None => format!("{} {} .\n", operation, triple),
+    }
+}
+
+/// Formats a triple as SPARQL quad data, wrapping it in a `GRAPH` block if it has a graph name.
+fn sparql_quad_data(triple: &str, graph: Option<&str>) -> String {
+    match graph {
+        Some(graph) => format!("  GRAPH {} {{ {} . }}\n", graph, triple),
+        None => format!("  {} .\n", triple),
+    }
+}
+
+/// Writes every row of a changeset table using the given line formatter.
+///
+/// Rows are passed to `format_row` as the `s p o` triple and the optional graph name.
+fn write_changeset_rows<W: Write>(
+    conn: &Connection,
+    table: &str,
+    has_named_graph: bool,
+    writer: &mut W,
+    format_row: impl Fn(&str, Option<&str>) -> String,
+) -> Result<(), CottasError> {
+    let select = if has_named_graph {
+        "s, p, o, g"
+    } else {
+        "s, p, o"
+    };
+    let query = format!("SELECT {} FROM {}", select, table);
+
+    let mut stmt = conn.prepare(&query)?;
+    let mut rows = stmt.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let triple = format!(
+            "{} {} {}",
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        );
+        let graph: Option<String> = if has_named_graph { row.get(3)? } else { None };
+        writer.write_all(format_row(&triple, graph.as_deref()).as_bytes())?;
     }
 
     Ok(())
@@ -0,0 +1,340 @@
+//! Persistent handle on a Cottas file that reuses a single DuckDB connection.
+
+use crate::duckdb::{
+    check_cottas_file, connection_in_memory, estimate_count_with_connection, has_column,
+    has_cottas_columns, info_with_connection, parquet_columns, read_kv_metadata,
+    search_iter_with_connection, search_rows, translate_pruned_tp_tuple, CottasInfo, SearchIter,
+};
+use crate::error::CottasError;
+use crate::export::{write_quads_to_file, CompressedWriter};
+use crate::parser::{parse_tp_with_namespaces, rdf_format_from_name, Namespaces};
+use crate::utils::{build_order_by, Compression};
+use duckdb::Connection;
+use oxrdfio::RdfFormat;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// An open Cottas file.
+///
+/// The file is checked and its schema and key-value metadata are read once, when it is opened.
+/// Queries run on clones of one DuckDB connection that caches the Parquet footer, so repeated
+/// lookups do not re-read it. Only [`CottasFile::iter`] streams its rows from a background
+/// thread; other lookups run on the calling thread. Cloning a `CottasFile` is cheap and clones
+/// can be sent to other threads, which then query the file concurrently.
+#[derive(Clone)]
+pub struct CottasFile {
+    inner: Arc<CottasFileInner>,
+}
+
+/// State shared by the clones of a [`CottasFile`].
+struct CottasFileInner {
+    path: String,
+    conn: Mutex<Connection>,
+    quad_mode: bool,
+    kv_metadata: HashMap<String, String>,
+    namespaces: Namespaces,
+}
+
+impl CottasFile {
+    /// Opens a Cottas file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the Cottas file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CottasError::InvalidCottasFile`] if the file is not a Cottas file, or an error
+    /// if DuckDB cannot read it.
+    pub fn open(path: &str) -> Result<Self, CottasError> {
+        let conn = connection_in_memory()?;
+        conn.execute_batch("SET parquet_metadata_cache = true;")?;
+        check_cottas_file(&conn, path)?;
+
+        let quad_mode = has_column(&conn, path, "g")?;
+        let kv_metadata = read_kv_metadata(&conn, path)?;
+        let namespaces = Namespaces::from_kv_metadata(&kv_metadata);
+
+        Ok(Self {
+            inner: Arc::new(CottasFileInner {
+                path: path.to_string(),
+                conn: Mutex::new(conn),
+                quad_mode,
+                kv_metadata,
+                namespaces,
+            }),
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    /// Returns `true` if the file holds quads (has a graph column).
+    pub fn quad_mode(&self) -> bool {
+        self.inner.quad_mode
+    }
+
+    /// Returns the key-value metadata of the file.
+    pub fn kv_metadata(&self) -> &HashMap<String, String> {
+        &self.inner.kv_metadata
+    }
+
+    /// Returns the prefixes and base IRI stored in the file.
+    pub fn namespaces(&self) -> &Namespaces {
+        &self.inner.namespaces
+    }
+
+    /// Returns a new connection to the shared DuckDB database.
+    fn connection(&self) -> Result<Connection, CottasError> {
+        let conn = self
+            .inner
+            .conn
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(conn.try_clone()?)
+    }
+
+    /// Parses a triple or quad pattern, resolving prefixed names with the prefixes of the file.
+    fn parse_pattern(&self, triple_pattern: &str) -> Result<Vec<String>, CottasError> {
+        parse_tp_with_namespaces(triple_pattern, &self.inner.namespaces)
+    }
+
+    /// Lazily searches for matches of a triple or quad pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SearchIter)` - An iterator over the matching rows as vectors of strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed.
+    pub fn iter(&self, triple_pattern: &str) -> Result<SearchIter, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        let conn = self.connection()?;
+        let sql = translate_pruned_tp_tuple(&conn, &self.inner.path, &tp_tuple)?;
+
+        Ok(search_iter_with_connection(conn, sql, tp_tuple.len()))
+    }
+
+    /// Searches for matches of a triple or quad pattern.
+    ///
+    /// Unlike [`CottasFile::iter`], the query runs on the calling thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Vec<String>>)` - Query results as vectors of strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed or the query fails.
+    pub fn search(&self, triple_pattern: &str) -> Result<Vec<Vec<String>>, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        let conn = self.connection()?;
+        let sql = translate_pruned_tp_tuple(&conn, &self.inner.path, &tp_tuple)?;
+
+        search_rows(&conn, &sql, tp_tuple.len())
+    }
+
+    /// Counts the matches of a triple or quad pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed or the query fails.
+    pub fn count(&self, triple_pattern: &str) -> Result<u64, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        let conn = self.connection()?;
+        let sql = translate_pruned_tp_tuple(&conn, &self.inner.path, &tp_tuple)?;
+
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM ({})", sql), [], |row| {
+            row.get(0)
+        })?;
+        Ok(count as u64)
+    }
+
+    /// Builds the query of the matches of a parsed pattern, sorted by the index of the file.
+    ///
+    /// Matches of a triple pattern in a quad file are deduplicated across graphs.
+    fn ordered_query(&self, conn: &Connection, tp_tuple: &[String]) -> Result<String, CottasError> {
+        let index = self
+            .inner
+            .kv_metadata
+            .get("index")
+            .map(String::as_str)
+            .unwrap_or("spo");
+        let sql = translate_pruned_tp_tuple(conn, &self.inner.path, tp_tuple)?;
+
+        if tp_tuple.len() == 3 {
+            let index = index.replace(['g', 'G'], "");
+            let distinct = if self.inner.quad_mode {
+                "DISTINCT "
+            } else {
+                ""
+            };
+            Ok(format!(
+                "SELECT {}* FROM ({}) {}",
+                distinct,
+                sql,
+                build_order_by(&index, false)?
+            ))
+        } else {
+            Ok(format!(
+                "SELECT * FROM ({}) {}",
+                sql,
+                build_order_by(index, true)?
+            ))
+        }
+    }
+
+    /// Reads a page of the matches of a triple or quad pattern.
+    ///
+    /// Matches are sorted by the index of the file, so pages are stable across calls, and
+    /// matches of a triple pattern in a quad file are deduplicated across graphs.
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    /// * `offset` - Number of matches to skip.
+    /// * `limit` - Maximum number of matches to return.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed or the query fails.
+    pub fn page(
+        &self,
+        triple_pattern: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<Vec<String>>, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        let conn = self.connection()?;
+        let sql = format!(
+            "{} LIMIT {} OFFSET {}",
+            self.ordered_query(&conn, &tp_tuple)?,
+            limit,
+            offset
+        );
+        search_rows(&conn, &sql, tp_tuple.len())
+    }
+
+    /// Counts the matches of a triple or quad pattern as returned by [`CottasFile::page`].
+    pub(crate) fn page_count(&self, triple_pattern: &str) -> Result<u64, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        let conn = self.connection()?;
+        let count: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM ({})",
+                self.ordered_query(&conn, &tp_tuple)?
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    /// Estimates the number of matches of a triple or quad pattern from Parquet metadata.
+    ///
+    /// See [`crate::duckdb::estimate_count_in_duckdb`].
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed or the metadata cannot be read.
+    pub fn estimate_count(&self, triple_pattern: &str) -> Result<u64, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        estimate_count_with_connection(&self.connection()?, &self.inner.path, &tp_tuple)
+    }
+
+    /// Extracts metadata and statistics from the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can no longer be read.
+    pub fn info(&self) -> Result<CottasInfo, CottasError> {
+        info_with_connection(&self.connection()?, &self.inner.path, None)
+    }
+
+    /// Extracts metadata and statistics from the file, including the number of row groups
+    /// that a search for a triple or quad pattern reads.
+    ///
+    /// See [`crate::duckdb::info_with_pattern_duckdb`].
+    ///
+    /// # Arguments
+    ///
+    /// * `triple_pattern` - The triple or quad pattern as a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is malformed or the file can no longer be read.
+    pub fn info_with_pattern(&self, triple_pattern: &str) -> Result<CottasInfo, CottasError> {
+        let tp_tuple = self.parse_pattern(triple_pattern)?;
+        info_with_connection(&self.connection()?, &self.inner.path, Some(&tp_tuple))
+    }
+
+    /// Verifies that the file still has the columns of a Cottas file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if valid, `Ok(false)` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can no longer be read.
+    pub fn verify(&self) -> Result<bool, CottasError> {
+        let cottas_columns = parquet_columns(&self.connection()?, &self.inner.path)?;
+        Ok(has_cottas_columns(&cottas_columns))
+    }
+
+    /// Writes the file as RDF to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The output sink.
+    /// * `format` - Optional format name; N-Triples, or N-Quads for quad data, by default.
+    /// * `compression` - Optional compression of the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format is unsupported or writing fails.
+    pub fn export<W: Write>(
+        &self,
+        writer: W,
+        format: Option<&str>,
+        compression: Option<Compression>,
+    ) -> Result<(), CottasError> {
+        let format = match format {
+            Some(name) => rdf_format_from_name(name)?,
+            None if self.inner.quad_mode => RdfFormat::NQuads,
+            None => RdfFormat::NTriples,
+        };
+
+        let mut writer = CompressedWriter::new(writer, compression)?;
+        write_quads_to_file(
+            &self.connection()?,
+            &self.inner.path,
+            self.inner.quad_mode,
+            format,
+            &self.inner.namespaces,
+            &mut writer,
+        )?;
+        writer.finish()?;
+        Ok(())
+    }
+}
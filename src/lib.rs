@@ -2,60 +2,282 @@
 //!
 //! # Modules
 //! - `duckdb`: DuckDB integration and utilities.
+//! - `error`: The error type of the library.
 //! - `export`: Exporting data to Cottas format.
+//! - `file`: Persistent handle on a Cottas file.
 //! - `parser`: RDF file parsing utilities.
+//! - `server`: SPARQL endpoint and Triple Pattern Fragments over HTTP.
+//! - `sparql`: SPARQL query evaluation.
 //! - `utils`: Helper functions.
 
 pub mod duckdb;
+pub mod error;
 pub mod export;
+pub mod file;
 pub mod parser;
+pub mod server;
+pub mod sparql;
 pub mod utils;
 
-use crate::duckdb::{diff_duckdb, info_duckdb, verify_duckdb, CottasInfo};
+use crate::duckdb::{
+    changeset_duckdb, changeset_writer_duckdb, diff_duckdb, info_duckdb, info_with_pattern_duckdb,
+    intersect_duckdb, patch_duckdb, symdiff_duckdb, verify_duckdb, CottasInfo,
+};
 pub use duckdb::{
-    cat_duckdb, connection_in_memory, has_column, load_into_duckdb, search_in_duckdb,
+    bgp_in_duckdb, cat_duckdb, check_cottas_file, connection_in_memory, connection_on_disk,
+    estimate_count_in_duckdb, has_column, load_into_duckdb, prune_row_groups,
+    search_bindings_in_duckdb, search_in_duckdb, search_iter_in_duckdb, search_quads_in_duckdb,
+    stream_into_duckdb, ChangesetOutput, ChangesetSummary, GraphChanges, MixedGraphMode,
+    PatchInput, RowGroup, SearchIter,
+};
+pub use error::CottasError;
+pub use export::{
+    export_to_cottas, write_changeset_to_file, write_quads_to_file, ChangesetFormat,
+    CompressedWriter,
+};
+pub use file::CottasFile;
+use oxrdf::{Quad, Term, Triple};
+pub use parser::{
+    open_rdf_input, parse_bgp_with_namespaces, parse_quad, parse_rdf_file, parse_rdf_patch,
+    parse_rdf_quads, parse_rdf_reader, parse_term, parse_tp_with_namespaces, rdf_format,
+    rdf_format_from_name, Namespaces, RdfQuadReader,
 };
-pub use export::{export_to_cottas, write_quads_to_file};
-pub use parser::parse_rdf_file;
-use std::error::Error;
+pub use server::{
+    negotiate_graph_format, negotiate_rdf_format, negotiate_results_format, serve, serve_tpf,
+    MAX_QUERY_BYTES, SPARQL_PATH, TPF_PAGE_SIZE, TPF_PATH,
+};
+pub use sparesults::QueryResultsFormat;
+pub use sparql::{
+    query_in_duckdb, write_query_results, write_query_results_with_rdf_format, QueryResults,
+};
+use std::collections::HashMap;
 use std::fs::File;
-pub use utils::extract_format;
+use std::io::{Read, Write};
+pub use utils::{
+    extract_format, is_valid_index, strip_compression_extension, Compression, ParquetCodec,
+    ParquetVersion, WriteOptions, DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATIO,
+    DEFAULT_ROW_GROUP_SIZE,
+};
 
 /// Converts an RDF file to a Cottas file, using the specified index.
 ///
+/// The quads are streamed from the parser into a temporary on-disk DuckDB database, so
+/// the input does not need to fit in memory. Compressed files (e.g., `data.nt.gz`) are
+/// decompressed while they are parsed.
+///
 /// # Arguments
 /// * `rdf_file_path` - Path to the input RDF file.
 /// * `cottas_file_path` - Path to the output Cottas file.
-/// * `index` - Index type (e.g., "spo").
+/// * `index` - Index type (e.g., "spo", or "gspo" for quad data).
 ///
 /// # Errors
-/// Returns an error if parsing, loading, or exporting fails.
+/// Returns an error if the index is not valid for the data, or if parsing, loading, or exporting fails.
 pub fn rdf2_cottas(
     rdf_file_path: &str,
     cottas_file_path: &str,
     index: &str,
-) -> Result<(), Box<dyn Error>> {
-    let quads = parse_rdf_file(rdf_file_path)?;
-    let quad_mode = quads.iter().any(|q| q.3.is_some());
-    let conn = load_into_duckdb(&quads);
-    export_to_cottas(&conn, index, cottas_file_path, quad_mode)?;
+) -> Result<(), CottasError> {
+    rdf2_cottas_with_options(
+        rdf_file_path,
+        cottas_file_path,
+        index,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`rdf2_cottas`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `rdf_file_path` - Path to the input RDF file.
+/// * `cottas_file_path` - Path to the output Cottas file.
+/// * `index` - Index type (e.g., "spo", or "gspo" for quad data).
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if the index is not valid for the data, or if parsing, loading, or exporting fails.
+pub fn rdf2_cottas_with_options(
+    rdf_file_path: &str,
+    cottas_file_path: &str,
+    index: &str,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    if !is_valid_index(index) {
+        return Err(CottasError::invalid_index(
+            index,
+            "expected a permutation of `spo` or `gspo`",
+        ));
+    }
+
+    quads_to_cottas(
+        parse_rdf_quads(rdf_file_path)?,
+        cottas_file_path,
+        index,
+        write_options,
+    )
+}
+
+/// Converts RDF read from any source (e.g., standard input) to a Cottas file.
+///
+/// # Arguments
+/// * `reader` - The uncompressed RDF input.
+/// * `format` - Format name of the input (e.g., "nt", "turtle", or "trig").
+/// * `cottas_file_path` - Path to the output Cottas file.
+/// * `index` - Index type (e.g., "spo", or "gspo" for quad data).
+///
+/// # Errors
+/// Returns an error if the format or index is not valid, or if parsing, loading, or exporting fails.
+pub fn rdf2_cottas_reader(
+    reader: impl Read + 'static,
+    format: &str,
+    cottas_file_path: &str,
+    index: &str,
+) -> Result<(), CottasError> {
+    rdf2_cottas_reader_with_options(
+        reader,
+        format,
+        cottas_file_path,
+        index,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`rdf2_cottas_reader`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `reader` - The uncompressed RDF input.
+/// * `format` - Format name of the input (e.g., "nt", "turtle", or "trig").
+/// * `cottas_file_path` - Path to the output Cottas file.
+/// * `index` - Index type (e.g., "spo", or "gspo" for quad data).
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if the format or index is not valid, or if parsing, loading, or exporting fails.
+pub fn rdf2_cottas_reader_with_options(
+    reader: impl Read + 'static,
+    format: &str,
+    cottas_file_path: &str,
+    index: &str,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    if !is_valid_index(index) {
+        return Err(CottasError::invalid_index(
+            index,
+            "expected a permutation of `spo` or `gspo`",
+        ));
+    }
+
+    let format = rdf_format_from_name(format)?;
+    quads_to_cottas(
+        parse_rdf_reader(reader, format),
+        cottas_file_path,
+        index,
+        write_options,
+    )
+}
+
+/// Streams parsed quads into a temporary on-disk DuckDB database and exports them.
+fn quads_to_cottas(
+    mut quads: RdfQuadReader,
+    cottas_file_path: &str,
+    index: &str,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    let temp_dir = tempfile::tempdir()?;
+    let conn = connection_on_disk(temp_dir.path())?;
+    let quad_mode = stream_into_duckdb(&conn, &mut quads)?;
+    let metadata = quads.namespaces().to_kv_metadata();
+    export_to_cottas(
+        &conn,
+        index,
+        cottas_file_path,
+        quad_mode,
+        &metadata,
+        write_options,
+    )?;
     Ok(())
 }
 
 /// Converts a Cottas file back to RDF format.
 ///
+/// The serialization format is taken from the extension of the output file. Files with an
+/// unknown extension are written as N-Triples, or N-Quads for quad data. Output files ending
+/// in `.gz` or `.zst` are compressed (e.g., `data.ttl.gz`).
+///
 /// # Arguments
 /// * `cottas_file_path` - Path to the input Cottas file.
 /// * `rdf_file_path` - Path to the output RDF file.
 ///
 /// # Errors
-/// Returns an error if file creation or writing fails.
-pub fn cottas2_rdf(cottas_file_path: &str, rdf_file_path: &str) -> Result<(), Box<dyn Error>> {
-    let conn = connection_in_memory();
-    let has_named_graph = has_column(&conn, cottas_file_path, "g")?;
-    let mut file = File::create(rdf_file_path)?;
-    write_quads_to_file(&conn, cottas_file_path, has_named_graph, &mut file)?;
-    Ok(())
+/// Returns an error if file creation or writing fails. The output file is left untouched if
+/// the input cannot be read.
+pub fn cottas2_rdf(cottas_file_path: &str, rdf_file_path: &str) -> Result<(), CottasError> {
+    cottas2_rdf_with_format(cottas_file_path, rdf_file_path, None)
+}
+
+/// Same as [`cottas2_rdf`], serializing the output in the given format.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the input Cottas file.
+/// * `rdf_file_path` - Path to the output RDF file.
+/// * `format` - Optional format name (e.g., "turtle", "trig", "xml", or "jsonld"); taken from
+///   the extension of the output file by default.
+///
+/// # Errors
+/// Returns an error if the format is unsupported, or if file creation or writing fails. The
+/// output file is left untouched if the format is unsupported or the input cannot be read.
+pub fn cottas2_rdf_with_format(
+    cottas_file_path: &str,
+    rdf_file_path: &str,
+    format: Option<&str>,
+) -> Result<(), CottasError> {
+    cottas2_rdf_with_compression(cottas_file_path, rdf_file_path, format, None)
+}
+
+/// Converts a Cottas file back to RDF format with the given output compression.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the input Cottas file.
+/// * `rdf_file_path` - Path to the output RDF file.
+/// * `format` - Optional format name; taken from the extension of the output file by default.
+/// * `compression` - Optional compression; taken from the extension of the output file by default.
+///
+/// # Errors
+/// Returns an error if the format is unsupported, or if file creation or writing fails. The
+/// output file is left untouched if the format is unsupported or the input cannot be read.
+pub fn cottas2_rdf_with_compression(
+    cottas_file_path: &str,
+    rdf_file_path: &str,
+    format: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<(), CottasError> {
+    let format = format.or_else(|| extract_format(rdf_file_path));
+    if let Some(format) = format {
+        rdf_format_from_name(format)?;
+    }
+    let compression = compression.or_else(|| Compression::from_path(rdf_file_path));
+    let dataset = CottasFile::open(cottas_file_path)?;
+
+    let file = File::create(rdf_file_path)?;
+    dataset.export(file, format, compression)
+}
+
+/// Converts a Cottas file back to RDF, writing it to any sink (e.g., standard output).
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the input Cottas file.
+/// * `writer` - The output sink.
+/// * `format` - Optional format name; N-Triples, or N-Quads for quad data, by default.
+/// * `compression` - Optional compression of the output.
+///
+/// # Errors
+/// Returns an error if the format is unsupported or writing fails.
+pub fn cottas2_rdf_writer<W: Write>(
+    cottas_file_path: &str,
+    writer: W,
+    format: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<(), CottasError> {
+    CottasFile::open(cottas_file_path)?.export(writer, format, compression)
 }
 
 /// Searches for triples/quads in a Cottas file matching a pattern.
@@ -72,10 +294,126 @@ pub fn cottas2_rdf(cottas_file_path: &str, rdf_file_path: &str) -> Result<(), Bo
 pub fn search(
     cottas_file_path: &str,
     triple_pattern: &str,
-) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+) -> Result<Vec<Vec<String>>, CottasError> {
     search_in_duckdb(cottas_file_path, triple_pattern)
 }
 
+/// Lazily searches for triples/quads in a Cottas file matching a pattern.
+///
+/// Rows are yielded as soon as DuckDB produces them, and dropping the iterator stops the search.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the Cottas file.
+/// * `triple_pattern` - Pattern to search for.
+///
+/// # Returns
+/// An iterator over the matching results.
+///
+/// # Errors
+/// Returns an error if the search cannot be started. Query errors are reported by the iterator.
+pub fn search_iter(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<SearchIter, CottasError> {
+    search_iter_in_duckdb(cottas_file_path, triple_pattern)
+}
+
+/// Searches a Cottas file for a pattern and returns the bindings of its variables.
+///
+/// Repeated variables must bind the same term, so `?s ?p ?s` only matches self-loops.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the Cottas file.
+/// * `triple_pattern` - Pattern to search for.
+///
+/// # Returns
+/// One mapping from variable name (without `?`) to `oxrdf` term per match.
+///
+/// # Errors
+/// Returns an error if the search fails or a result is not valid RDF.
+pub fn search_bindings(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<HashMap<String, Term>>, CottasError> {
+    search_bindings_in_duckdb(cottas_file_path, triple_pattern)
+}
+
+/// Evaluates a basic graph pattern over one or more Cottas files.
+///
+/// # Arguments
+/// * `cottas_file_paths` - Cottas files queried as a single dataset.
+/// * `bgp` - Triple patterns sharing variables, separated by `.` (e.g., "?p a <http://ex/Person> . ?p <http://ex/name> ?n").
+///
+/// # Returns
+/// One mapping from variable name (without `?`) to `oxrdf` term per solution.
+///
+/// # Errors
+/// Returns an error if the pattern is malformed or the query fails.
+pub fn bgp(
+    cottas_file_paths: &[String],
+    bgp: &str,
+) -> Result<Vec<HashMap<String, Term>>, CottasError> {
+    bgp_in_duckdb(cottas_file_paths, bgp)
+}
+
+/// Evaluates a SPARQL query over one or more Cottas files.
+///
+/// # Arguments
+/// * `cottas_file_paths` - Cottas files queried as a single dataset.
+/// * `query` - A SPARQL 1.1 SELECT, ASK, or CONSTRUCT query.
+///
+/// # Returns
+/// The query solutions, boolean, or constructed triples.
+///
+/// # Errors
+/// Returns an error if the query is invalid, uses an unsupported feature, or fails.
+pub fn query(cottas_file_paths: &[String], query: &str) -> Result<QueryResults, CottasError> {
+    query_in_duckdb(cottas_file_paths, query)
+}
+
+/// Searches for quads in a Cottas file matching a pattern.
+///
+/// Matches of a triple pattern are returned in the default graph.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the Cottas file.
+/// * `triple_pattern` - Pattern to search for.
+///
+/// # Returns
+/// A vector of matching `oxrdf` quads.
+///
+/// # Errors
+/// Returns an error if the search fails or a result is not valid RDF.
+pub fn search_quads(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<Quad>, CottasError> {
+    search_quads_in_duckdb(cottas_file_path, triple_pattern)
+}
+
+/// Searches for triples in a Cottas file matching a pattern.
+///
+/// The graph names of quad matches are dropped.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the Cottas file.
+/// * `triple_pattern` - Pattern to search for.
+///
+/// # Returns
+/// A vector of matching `oxrdf` triples.
+///
+/// # Errors
+/// Returns an error if the search fails or a result is not valid RDF.
+pub fn search_triples(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<Vec<Triple>, CottasError> {
+    Ok(search_quads_in_duckdb(cottas_file_path, triple_pattern)?
+        .into_iter()
+        .map(Triple::from)
+        .collect())
+}
+
 /// Concatenates multiple Cottas files into one.
 ///
 /// # Arguments
@@ -91,14 +429,76 @@ pub fn cat(
     cottas_cat_file_path: &str,
     index: Option<&str>,
     remove_input_files: Option<bool>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), CottasError> {
+    cat_with_options(
+        cottas_file_paths,
+        cottas_cat_file_path,
+        index,
+        remove_input_files,
+        None,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`cat`], placing the triples of triple-only inputs mixed with quad inputs in the
+/// graph selected by `graph_mode`.
+///
+/// # Arguments
+/// * `cottas_file_paths` - Array of input file paths
+/// * `cottas_cat_file_path` - Output file path.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after concatenation.
+/// * `graph_mode` - Graph for triples of triple-only inputs mixed with quad inputs.
+///
+/// # Errors
+/// Returns an error if concatenation fails.
+pub fn cat_with_graph_mode(
+    cottas_file_paths: &[String],
+    cottas_cat_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+    graph_mode: MixedGraphMode,
+) -> Result<(), CottasError> {
+    cat_with_options(
+        cottas_file_paths,
+        cottas_cat_file_path,
+        index,
+        remove_input_files,
+        Some(graph_mode),
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`cat`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_file_paths` - Array of input file paths
+/// * `cottas_cat_file_path` - Output file path.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after concatenation.
+/// * `graph_mode` - Optional graph for triples of triple-only inputs mixed with quad inputs.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if concatenation fails.
+pub fn cat_with_options(
+    cottas_file_paths: &[String],
+    cottas_cat_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+    graph_mode: Option<MixedGraphMode>,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
     let index = index.unwrap_or("spo");
     let remove_input_files = remove_input_files.unwrap_or(false);
+    let graph_mode = graph_mode.unwrap_or_default();
     cat_duckdb(
         cottas_file_paths,
         cottas_cat_file_path,
         index,
         remove_input_files,
+        graph_mode,
+        write_options,
     )
 }
 
@@ -119,7 +519,37 @@ pub fn diff(
     cottas_diff_file_path: &str,
     index: Option<&str>,
     remove_input_files: Option<bool>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), CottasError> {
+    diff_with_options(
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_diff_file_path,
+        index,
+        remove_input_files,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`diff`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_file_1_path` - First input file.
+/// * `cottas_file_2_path` - Second input file.
+/// * `cottas_diff_file_path` - Output file for the diff.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after diff.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if diffing fails.
+pub fn diff_with_options(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_diff_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
     let index = index.unwrap_or("spo");
     let remove_input_files = remove_input_files.unwrap_or(false);
     diff_duckdb(
@@ -128,6 +558,273 @@ pub fn diff(
         cottas_diff_file_path,
         index,
         remove_input_files,
+        write_options,
+    )
+}
+
+/// Computes the intersection of two Cottas files.
+///
+/// # Arguments
+/// * `cottas_file_1_path` - First input file.
+/// * `cottas_file_2_path` - Second input file.
+/// * `cottas_intersect_file_path` - Output file for the intersection.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after the intersection.
+///
+/// # Errors
+/// Returns an error if the intersection fails.
+pub fn intersect(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_intersect_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+) -> Result<(), CottasError> {
+    intersect_with_options(
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_intersect_file_path,
+        index,
+        remove_input_files,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`intersect`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_file_1_path` - First input file.
+/// * `cottas_file_2_path` - Second input file.
+/// * `cottas_intersect_file_path` - Output file for the intersection.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after the intersection.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if the intersection fails.
+pub fn intersect_with_options(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_intersect_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    let index = index.unwrap_or("spo");
+    let remove_input_files = remove_input_files.unwrap_or(false);
+    intersect_duckdb(
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_intersect_file_path,
+        index,
+        remove_input_files,
+        write_options,
+    )
+}
+
+/// Computes the symmetric difference of two Cottas files.
+///
+/// # Arguments
+/// * `cottas_file_1_path` - First input file.
+/// * `cottas_file_2_path` - Second input file.
+/// * `cottas_symdiff_file_path` - Output file for the symmetric difference.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after the symmetric difference.
+///
+/// # Errors
+/// Returns an error if the symmetric difference fails.
+pub fn symdiff(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_symdiff_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+) -> Result<(), CottasError> {
+    symdiff_with_options(
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_symdiff_file_path,
+        index,
+        remove_input_files,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`symdiff`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_file_1_path` - First input file.
+/// * `cottas_file_2_path` - Second input file.
+/// * `cottas_symdiff_file_path` - Output file for the symmetric difference.
+/// * `index` - Optional index type.
+/// * `remove_input_files` - Optionally remove input files after the symmetric difference.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if the symmetric difference fails.
+pub fn symdiff_with_options(
+    cottas_file_1_path: &str,
+    cottas_file_2_path: &str,
+    cottas_symdiff_file_path: &str,
+    index: Option<&str>,
+    remove_input_files: Option<bool>,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    let index = index.unwrap_or("spo");
+    let remove_input_files = remove_input_files.unwrap_or(false);
+    symdiff_duckdb(
+        cottas_file_1_path,
+        cottas_file_2_path,
+        cottas_symdiff_file_path,
+        index,
+        remove_input_files,
+        write_options,
+    )
+}
+
+/// Computes the changeset between two versions of a Cottas file.
+///
+/// # Arguments
+/// * `cottas_old_file_path` - Old version of the file.
+/// * `cottas_new_file_path` - New version of the file.
+/// * `output` - Cottas files or RDF Patch / SPARQL Update document to write the changes to.
+/// * `index` - Optional index type for Cottas outputs.
+/// * `per_graph` - Optionally include counts per named graph in the summary.
+///
+/// # Returns
+/// `ChangesetSummary` with the number of added and removed triples or quads.
+///
+/// # Errors
+/// Returns an error if computing or writing the changeset fails.
+pub fn changeset(
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    output: ChangesetOutput,
+    index: Option<&str>,
+    per_graph: Option<bool>,
+) -> Result<ChangesetSummary, CottasError> {
+    changeset_with_options(
+        cottas_old_file_path,
+        cottas_new_file_path,
+        output,
+        index,
+        per_graph,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`changeset`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_old_file_path` - Old version of the file.
+/// * `cottas_new_file_path` - New version of the file.
+/// * `output` - Cottas files or RDF Patch / SPARQL Update document to write the changes to.
+/// * `index` - Optional index type for Cottas outputs.
+/// * `per_graph` - Optionally include counts per named graph in the summary.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Returns
+/// `ChangesetSummary` with the number of added and removed triples or quads.
+///
+/// # Errors
+/// Returns an error if computing or writing the changeset fails.
+pub fn changeset_with_options(
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    output: ChangesetOutput,
+    index: Option<&str>,
+    per_graph: Option<bool>,
+    write_options: &WriteOptions,
+) -> Result<ChangesetSummary, CottasError> {
+    let index = index.unwrap_or("spo");
+    let per_graph = per_graph.unwrap_or(false);
+    changeset_duckdb(
+        cottas_old_file_path,
+        cottas_new_file_path,
+        output,
+        index,
+        per_graph,
+        write_options,
+    )
+}
+
+/// Computes the changeset between two versions of a Cottas file, writing it as an RDF Patch
+/// or SPARQL Update document to any sink (e.g., standard output).
+///
+/// # Arguments
+/// * `cottas_old_file_path` - Old version of the file.
+/// * `cottas_new_file_path` - New version of the file.
+/// * `format` - Format of the changeset document.
+/// * `writer` - The output sink.
+/// * `compression` - Optional compression of the output.
+/// * `per_graph` - Optionally include counts per named graph in the summary.
+///
+/// # Returns
+/// `ChangesetSummary` with the number of added and removed triples or quads.
+///
+/// # Errors
+/// Returns an error if computing or writing the changeset fails.
+pub fn changeset_writer<W: Write>(
+    cottas_old_file_path: &str,
+    cottas_new_file_path: &str,
+    format: ChangesetFormat,
+    writer: W,
+    compression: Option<Compression>,
+    per_graph: Option<bool>,
+) -> Result<ChangesetSummary, CottasError> {
+    changeset_writer_duckdb(
+        cottas_old_file_path,
+        cottas_new_file_path,
+        format,
+        writer,
+        compression,
+        per_graph.unwrap_or(false),
+    )
+}
+
+/// Applies additions and deletions to a Cottas file, producing its new version.
+///
+/// # Arguments
+/// * `cottas_base_file_path` - Base version of the file.
+/// * `input` - Cottas files or RDF Patch document with the changes to apply.
+/// * `cottas_patched_file_path` - Output file for the new version.
+///
+/// # Errors
+/// Returns an error if reading the changes or writing the new version fails.
+pub fn patch(
+    cottas_base_file_path: &str,
+    input: PatchInput,
+    cottas_patched_file_path: &str,
+) -> Result<(), CottasError> {
+    patch_with_options(
+        cottas_base_file_path,
+        input,
+        cottas_patched_file_path,
+        &WriteOptions::default(),
+    )
+}
+
+/// Same as [`patch`], writing the output with the given Parquet writer options.
+///
+/// # Arguments
+/// * `cottas_base_file_path` - Base version of the file.
+/// * `input` - Cottas files or RDF Patch document with the changes to apply.
+/// * `cottas_patched_file_path` - Output file for the new version.
+/// * `write_options` - Options of the Parquet writer.
+///
+/// # Errors
+/// Returns an error if reading the changes or writing the new version fails.
+pub fn patch_with_options(
+    cottas_base_file_path: &str,
+    input: PatchInput,
+    cottas_patched_file_path: &str,
+    write_options: &WriteOptions,
+) -> Result<(), CottasError> {
+    patch_duckdb(
+        cottas_base_file_path,
+        input,
+        cottas_patched_file_path,
+        write_options,
     )
 }
 
@@ -141,10 +838,31 @@ pub fn diff(
 ///
 /// # Errors
 /// Returns an error if info retrieval fails.
-pub fn info(cottas_file_path: &str) -> Result<CottasInfo, Box<dyn Error>> {
+pub fn info(cottas_file_path: &str) -> Result<CottasInfo, CottasError> {
     info_duckdb(cottas_file_path)
 }
 
+/// Retrieves information about a Cottas file, including the number of row groups that a
+/// search for a pattern reads.
+///
+/// Row groups whose statistics or bloom filters exclude a constant of the pattern are not read.
+///
+/// # Arguments
+/// * `cottas_file_path` - Path to the Cottas file.
+/// * `triple_pattern` - Triple or quad pattern.
+///
+/// # Returns
+/// `CottasInfo` struct with file metadata and `touched_row_groups` set.
+///
+/// # Errors
+/// Returns an error if the pattern is malformed or info retrieval fails.
+pub fn info_with_pattern(
+    cottas_file_path: &str,
+    triple_pattern: &str,
+) -> Result<CottasInfo, CottasError> {
+    info_with_pattern_duckdb(cottas_file_path, triple_pattern)
+}
+
 /// Verifies the integrity of a Cottas file.
 ///
 /// # Arguments
@@ -155,6 +873,6 @@ pub fn info(cottas_file_path: &str) -> Result<CottasInfo, Box<dyn Error>> {
 ///
 /// # Errors
 /// Returns an error if verification fails.
-pub fn verify(cottas_file_path: &str) -> Result<bool, Box<dyn Error>> {
+pub fn verify(cottas_file_path: &str) -> Result<bool, CottasError> {
     verify_duckdb(cottas_file_path)
 }
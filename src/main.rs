@@ -1,5 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::error::Error;
+use std::io::{self, Write};
 
 use cottas_rs::*;
 
@@ -11,19 +12,84 @@ struct Cli {
     command: Commands,
 }
 
+/// Parquet writer options of the commands that write COTTAS files
+#[derive(Args)]
+struct WriteArgs {
+    /// Compression codec of the Parquet columns
+    #[arg(long, value_enum, default_value_t = ParquetCodec::Zstd)]
+    codec: ParquetCodec,
+
+    /// Compression level; only supported by zstd, which uses 22 by default
+    #[arg(long)]
+    compression_level: Option<i32>,
+
+    /// Maximum number of rows per row group
+    #[arg(long, default_value_t = DEFAULT_ROW_GROUP_SIZE)]
+    row_group_size: u64,
+
+    /// Maximum size in bytes of the dictionary page of a string column
+    #[arg(long)]
+    dictionary_page_size_limit: Option<u64>,
+
+    /// Parquet format version
+    #[arg(long, value_enum, default_value_t = ParquetVersion::V2)]
+    parquet_version: ParquetVersion,
+
+    /// Write the columns without dictionary encoding, and thus without bloom filters
+    #[arg(long)]
+    no_dictionary: bool,
+
+    /// Target false positive ratio of the column bloom filters
+    #[arg(long, default_value_t = DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATIO)]
+    bloom_filter_fpp: f64,
+}
+
+impl From<WriteArgs> for WriteOptions {
+    fn from(args: WriteArgs) -> Self {
+        Self {
+            codec: args.codec,
+            compression_level: args.compression_level,
+            row_group_size: Some(args.row_group_size),
+            dictionary_page_size_limit: args.dictionary_page_size_limit,
+            parquet_version: args.parquet_version,
+            dictionary_encoding: !args.no_dictionary,
+            bloom_filter_false_positive_ratio: args.bloom_filter_fpp,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Convert RDF to COTTAS
     Rdf2Cottas {
+        /// Input RDF file, possibly compressed, or `-` for standard input
         rdf_file_path: String,
         cottas_file_path: String,
         index: String,
+
+        /// Input format (nt, nq, turtle, trig, xml, or jsonld); required for standard input
+        #[arg(short, long)]
+        format: Option<String>,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 
     /// Convert COTTAS to RDF
     Cottas2Rdf {
+        /// Input COTTAS file, or `-` for standard input
         cottas_file_path: String,
+
+        /// Output RDF file, or `-` for standard output
         rdf_file_path: String,
+
+        /// Output compression; taken from the extension (.gz or .zst) by default
+        #[arg(short, long, value_enum)]
+        compression: Option<Compression>,
+
+        /// Output format (nt, nq, turtle, trig, xml, or jsonld); taken from the extension by default
+        #[arg(short, long)]
+        format: Option<String>,
     },
 
     /// Search in a COTTAS file
@@ -32,6 +98,47 @@ enum Commands {
         triple_pattern: String,
     },
 
+    /// Evaluate a basic graph pattern over COTTAS files
+    Bgp {
+        /// Triple patterns separated by `.`
+        bgp: String,
+
+        #[arg(required = true)]
+        cottas_file_paths: Vec<String>,
+    },
+
+    /// Evaluate a SPARQL query over COTTAS files
+    Query {
+        /// SPARQL SELECT, ASK, or CONSTRUCT query
+        query: String,
+
+        #[arg(required = true)]
+        cottas_file_paths: Vec<String>,
+
+        /// Results format (json, xml, csv, or tsv); CONSTRUCT results are N-Triples
+        #[arg(short, long, default_value = "json")]
+        format: String,
+    },
+
+    /// Serve COTTAS files as a read-only SPARQL endpoint
+    Serve {
+        #[arg(required = true)]
+        cottas_file_paths: Vec<String>,
+
+        /// Address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:7878")]
+        address: String,
+    },
+
+    /// Serve a COTTAS file as Triple Pattern Fragments
+    Tpf {
+        cottas_file_path: String,
+
+        /// Address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:7878")]
+        address: String,
+    },
+
     /// Concatenate multiple COTTAS files
     Cat {
         #[arg(required = true)]
@@ -45,6 +152,13 @@ enum Commands {
 
         #[arg(long)]
         remove_input_files: bool,
+
+        /// Graph for triples of triple-only inputs mixed with quad inputs
+        #[arg(long, value_enum)]
+        mixed_graphs: Option<MixedGraphMode>,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 
     /// Diff two COTTAS files
@@ -60,10 +174,114 @@ enum Commands {
 
         #[arg(long)]
         remove_input_files: bool,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Intersect two COTTAS files
+    Intersect {
+        cottas_file_1_path: String,
+        cottas_file_2_path: String,
+
+        #[arg(short, long)]
+        output: String,
+
+        #[arg(short, long)]
+        index: Option<String>,
+
+        #[arg(long)]
+        remove_input_files: bool,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Symmetric difference of two COTTAS files
+    Symdiff {
+        cottas_file_1_path: String,
+        cottas_file_2_path: String,
+
+        #[arg(short, long)]
+        output: String,
+
+        #[arg(short, long)]
+        index: Option<String>,
+
+        #[arg(long)]
+        remove_input_files: bool,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Compute the added and removed triples between two COTTAS files
+    Changeset {
+        cottas_old_file_path: String,
+        cottas_new_file_path: String,
+
+        /// Output COTTAS file for the added triples
+        #[arg(long, requires = "removed", conflicts_with_all = ["patch", "update"])]
+        added: Option<String>,
+
+        /// Output COTTAS file for the removed triples
+        #[arg(long, requires = "added")]
+        removed: Option<String>,
+
+        /// Output RDF Patch file, or `-` for standard output
+        #[arg(long, conflicts_with = "update")]
+        patch: Option<String>,
+
+        /// Output SPARQL Update file, or `-` for standard output
+        #[arg(long)]
+        update: Option<String>,
+
+        /// Compression of the RDF Patch or SPARQL Update output; taken from the extension (.gz or .zst) by default
+        #[arg(short, long, value_enum)]
+        compression: Option<Compression>,
+
+        #[arg(short, long)]
+        index: Option<String>,
+
+        /// Report counts per named graph
+        #[arg(long)]
+        per_graph: bool,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Apply additions and deletions to a COTTAS file
+    Patch {
+        cottas_base_file_path: String,
+
+        #[arg(short, long)]
+        output: String,
+
+        /// COTTAS file with the triples to add
+        #[arg(long, requires = "removed", conflicts_with = "patch")]
+        added: Option<String>,
+
+        /// COTTAS file with the triples to remove
+        #[arg(long, requires = "added")]
+        removed: Option<String>,
+
+        /// RDF Patch file with the changes
+        #[arg(long)]
+        patch: Option<String>,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 
     /// Show info about a COTTAS file
-    Info { cottas_file_path: String },
+    Info {
+        cottas_file_path: String,
+
+        /// Triple or quad pattern whose touched row groups are reported
+        #[arg(long)]
+        pattern: Option<String>,
+    },
 
     /// Verify a COTTAS file
     Verify { cottas_file_path: String },
@@ -77,38 +295,138 @@ fn main() -> Result<(), Box<dyn Error>> {
             rdf_file_path,
             cottas_file_path,
             index,
+            format,
+            write,
         } => {
-            rdf2_cottas(&rdf_file_path, &cottas_file_path, &index)?;
+            let write_options = WriteOptions::from(write);
+            match format {
+                Some(format) => {
+                    let reader = open_rdf_input(&rdf_file_path)?;
+                    rdf2_cottas_reader_with_options(
+                        reader,
+                        &format,
+                        &cottas_file_path,
+                        &index,
+                        &write_options,
+                    )?;
+                }
+                None if rdf_file_path == "-" => {
+                    return Err("Reading from standard input requires --format.".into());
+                }
+                None => rdf2_cottas_with_options(
+                    &rdf_file_path,
+                    &cottas_file_path,
+                    &index,
+                    &write_options,
+                )?,
+            }
         }
 
         Commands::Cottas2Rdf {
             cottas_file_path,
             rdf_file_path,
+            compression,
+            format,
         } => {
-            cottas2_rdf(&cottas_file_path, &rdf_file_path)?;
+            // Parquet cannot be read as a stream, so standard input is copied to a file
+            let stdin_copy = if cottas_file_path == "-" {
+                let mut file = tempfile::NamedTempFile::new()?;
+                io::copy(&mut io::stdin().lock(), &mut file)?;
+                Some(file)
+            } else {
+                None
+            };
+            let cottas_file_path = match &stdin_copy {
+                Some(file) => file.path().to_string_lossy().to_string(),
+                None => cottas_file_path,
+            };
+
+            if rdf_file_path == "-" {
+                cottas2_rdf_writer(
+                    &cottas_file_path,
+                    io::stdout().lock(),
+                    format.as_deref(),
+                    compression,
+                )?;
+            } else {
+                cottas2_rdf_with_compression(
+                    &cottas_file_path,
+                    &rdf_file_path,
+                    format.as_deref(),
+                    compression,
+                )?;
+            }
         }
 
         Commands::Search {
             cottas_file_path,
             triple_pattern,
         } => {
-            let results = search(&cottas_file_path, &triple_pattern)?;
-            for row in results {
-                println!("{}", row.join(" "));
+            let mut stdout = io::stdout().lock();
+            for row in search_iter(&cottas_file_path, &triple_pattern)? {
+                writeln!(stdout, "{}", row?.join(" "))?;
+            }
+        }
+
+        Commands::Bgp {
+            bgp: bgp_str,
+            cottas_file_paths,
+        } => {
+            let mut stdout = io::stdout().lock();
+            for solution in bgp(&cottas_file_paths, &bgp_str)? {
+                let mut bindings: Vec<_> = solution.into_iter().collect();
+                bindings.sort_by(|a, b| a.0.cmp(&b.0));
+                let line = bindings
+                    .iter()
+                    .map(|(variable, term)| format!("?{}={}", variable, term))
+                    .collect::<Vec<_>>()
+                    .join("\t");
+                writeln!(stdout, "{}", line)?;
             }
         }
 
+        Commands::Query {
+            query: query_str,
+            cottas_file_paths,
+            format,
+        } => {
+            let results_format = QueryResultsFormat::from_extension(&format)
+                .ok_or_else(|| format!("Unsupported results format `{}`.", format))?;
+            let results = query(&cottas_file_paths, &query_str)?;
+            write_query_results(&results, results_format, io::stdout().lock())?;
+        }
+
+        Commands::Serve {
+            cottas_file_paths,
+            address,
+        } => {
+            eprintln!("SPARQL endpoint at http://{}{}", address, SPARQL_PATH);
+            serve(&cottas_file_paths, &address)?;
+        }
+
+        Commands::Tpf {
+            cottas_file_path,
+            address,
+        } => {
+            eprintln!("Triple Pattern Fragments at http://{}{}", address, TPF_PATH);
+            serve_tpf(&cottas_file_path, &address)?;
+        }
+
         Commands::Cat {
             cottas_file_paths,
             output,
             index,
             remove_input_files,
+            mixed_graphs,
+            write,
         } => {
-            cat(
+            cat_with_options(
                 &cottas_file_paths,
                 &output,
                 index.as_deref(),
                 Some(remove_input_files),
+                mixed_graphs,
+                &write.into(),
             )?;
         }
 
@@ -118,18 +436,146 @@ fn main() -> Result<(), Box<dyn Error>> {
             output,
             index,
             remove_input_files,
+            write,
         } => {
-            diff(
+            diff_with_options(
                 &cottas_file_1_path,
                 &cottas_file_2_path,
                 &output,
                 index.as_deref(),
                 Some(remove_input_files),
+                &write.into(),
             )?;
         }
 
-        Commands::Info { cottas_file_path } => {
-            let info = info(&cottas_file_path)?;
+        Commands::Intersect {
+            cottas_file_1_path,
+            cottas_file_2_path,
+            output,
+            index,
+            remove_input_files,
+            write,
+        } => {
+            intersect_with_options(
+                &cottas_file_1_path,
+                &cottas_file_2_path,
+                &output,
+                index.as_deref(),
+                Some(remove_input_files),
+                &write.into(),
+            )?;
+        }
+
+        Commands::Symdiff {
+            cottas_file_1_path,
+            cottas_file_2_path,
+            output,
+            index,
+            remove_input_files,
+            write,
+        } => {
+            symdiff_with_options(
+                &cottas_file_1_path,
+                &cottas_file_2_path,
+                &output,
+                index.as_deref(),
+                Some(remove_input_files),
+                &write.into(),
+            )?;
+        }
+
+        Commands::Changeset {
+            cottas_old_file_path,
+            cottas_new_file_path,
+            added,
+            removed,
+            patch,
+            update,
+            compression,
+            index,
+            per_graph,
+            write,
+        } => {
+            let output = match (&added, &removed, &patch, &update) {
+                (Some(added), Some(removed), None, None) => {
+                    ChangesetOutput::Cottas { added, removed }
+                }
+                (None, None, Some(patch), None) => ChangesetOutput::RdfPatch(patch),
+                (None, None, None, Some(update)) => ChangesetOutput::SparqlUpdate(update),
+                _ => {
+                    return Err(
+                        "Specify either --added and --removed, --patch, or --update.".into(),
+                    )
+                }
+            };
+            let document = match output {
+                ChangesetOutput::Cottas { .. } => None,
+                ChangesetOutput::RdfPatch(path) => Some((ChangesetFormat::RdfPatch, path)),
+                ChangesetOutput::SparqlUpdate(path) => Some((ChangesetFormat::SparqlUpdate, path)),
+            };
+
+            match document {
+                // The summary goes to standard error, next to the document on standard output
+                Some((format, "-")) => {
+                    let summary = changeset_writer(
+                        &cottas_old_file_path,
+                        &cottas_new_file_path,
+                        format,
+                        io::stdout().lock(),
+                        compression,
+                        Some(per_graph),
+                    )?;
+                    eprintln!("{summary:#?}");
+                }
+                Some((format, path)) if compression.is_some() => {
+                    let summary = changeset_writer(
+                        &cottas_old_file_path,
+                        &cottas_new_file_path,
+                        format,
+                        std::fs::File::create(path)?,
+                        compression,
+                        Some(per_graph),
+                    )?;
+                    println!("{summary:#?}");
+                }
+                _ => {
+                    let summary = changeset_with_options(
+                        &cottas_old_file_path,
+                        &cottas_new_file_path,
+                        output,
+                        index.as_deref(),
+                        Some(per_graph),
+                        &write.into(),
+                    )?;
+                    println!("{summary:#?}");
+                }
+            }
+        }
+
+        Commands::Patch {
+            cottas_base_file_path,
+            output,
+            added,
+            removed,
+            patch: rdf_patch,
+            write,
+        } => {
+            let input = match (&added, &removed, &rdf_patch) {
+                (Some(added), Some(removed), None) => PatchInput::Cottas { added, removed },
+                (None, None, Some(rdf_patch)) => PatchInput::RdfPatch(rdf_patch),
+                _ => return Err("Specify either --added and --removed, or --patch.".into()),
+            };
+            patch_with_options(&cottas_base_file_path, input, &output, &write.into())?;
+        }
+
+        Commands::Info {
+            cottas_file_path,
+            pattern,
+        } => {
+            let info = match pattern {
+                Some(pattern) => info_with_pattern(&cottas_file_path, &pattern)?,
+                None => info(&cottas_file_path)?,
+            };
             println!("{info:#?}");
         }
 
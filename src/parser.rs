@@ -1,12 +1,255 @@
 //! RDF parser utilities for reading and handling triple/quad patterns.
 
-use crate::utils::extract_format;
-use oxrdf::{GraphName, Quad};
-use oxrdfio::{RdfFormat, RdfParser};
-use std::error::Error;
+use crate::error::CottasError;
+use crate::utils::{extract_format, Compression};
+use oxrdf::{GraphName, Quad, Term};
+use oxrdfio::{RdfFormat, RdfParser, ReaderQuadParser};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, ErrorKind};
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+/// A quad as subject, predicate, object, and optional graph name in N-Triples term syntax.
+pub type RdfQuad = (String, String, String, Option<String>);
+
+/// Determines the `oxrdfio` format of an RDF file from its extension.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the RDF file.
+///
+/// # Errors
+///
+/// Returns an error if the file extension is unsupported.
+pub fn rdf_format(path: &str) -> Result<RdfFormat, CottasError> {
+    let format_str = extract_format(path)
+        .ok_or_else(|| CottasError::Unsupported(format!("RDF extension for file: {}", path)))?;
+
+    rdf_format_from_name(format_str)
+}
+
+/// Determines the `oxrdfio` format from a format name.
+///
+/// The names returned by [`extract_format`] are accepted, as well as the common aliases
+/// `ntriples`, `nquads`, `ttl`, `rdfxml`, and `json-ld`.
+///
+/// # Arguments
+///
+/// * `name` - The format name (e.g., "turtle", "trig", "xml", or "jsonld").
+///
+/// # Errors
+///
+/// Returns an error if the format is unsupported.
+pub fn rdf_format_from_name(name: &str) -> Result<RdfFormat, CottasError> {
+    let format = match name.to_ascii_lowercase().as_str() {
+        "nt" | "ntriples" => Some(RdfFormat::NTriples),
+        "nq" | "nquads" => Some(RdfFormat::NQuads),
+        "turtle" | "ttl" => Some(RdfFormat::Turtle),
+        "trig" => Some(RdfFormat::TriG),
+        "xml" | "rdfxml" => Some(RdfFormat::RdfXml),
+        "jsonld" | "json-ld" => RdfFormat::from_extension("jsonld"),
+        _ => None,
+    };
+
+    format.ok_or_else(|| CottasError::Unsupported(format!("RDF format: {}", name)))
+}
+
+/// Converts an `oxrdf` quad into its subject, predicate, object, and optional graph strings.
+fn quad_to_tuple(quad: Quad) -> RdfQuad {
+    let g: Option<String> = match &quad.graph_name {
+        GraphName::NamedNode(node) => Some(node.to_string()),
+        GraphName::BlankNode(node) => Some(node.to_string()),
+        GraphName::DefaultGraph => None,
+    };
+
+    (
+        quad.subject.to_string(),
+        quad.predicate.to_string(),
+        quad.object.to_string(),
+        g,
+    )
+}
+
+/// Namespace prefixes and base IRI declared in an RDF document.
+///
+/// They are kept in the key-value metadata of Cottas files, so that serializations can reuse
+/// them and patterns can use prefixed names (e.g., `foaf:name`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Namespaces {
+    /// Namespace IRIs by prefix name (e.g., `foaf` for `http://xmlns.com/foaf/0.1/`).
+    pub prefixes: BTreeMap<String, String>,
+    /// The base IRI, if any.
+    pub base_iri: Option<String>,
+}
+
+impl Namespaces {
+    /// Metadata key of the prefixes, stored as one `PREFIX name: <iri>` line per prefix.
+    pub const PREFIXES_KEY: &'static str = "prefixes";
+
+    /// Metadata key of the base IRI.
+    pub const BASE_KEY: &'static str = "base";
+
+    /// Returns `true` if there are no prefixes and no base IRI.
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.base_iri.is_none()
+    }
+
+    /// Reads the namespaces from the key-value metadata of a Cottas file.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - The key-value metadata.
+    pub fn from_kv_metadata(metadata: &HashMap<String, String>) -> Self {
+        let prefixes = metadata
+            .get(Self::PREFIXES_KEY)
+            .map(|value| {
+                value
+                    .lines()
+                    .filter_map(|line| {
+                        let (name, iri) = line.trim().strip_prefix("PREFIX ")?.split_once(':')?;
+                        let iri = iri.trim().strip_prefix('<')?.strip_suffix('>')?;
+                        Some((name.to_string(), iri.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            prefixes,
+            base_iri: metadata.get(Self::BASE_KEY).cloned(),
+        }
+    }
+
+    /// Converts the namespaces to key-value metadata entries.
+    pub fn to_kv_metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        if !self.prefixes.is_empty() {
+            let prefixes = self
+                .prefixes
+                .iter()
+                .map(|(name, iri)| format!("PREFIX {}: <{}>", name, iri))
+                .collect::<Vec<_>>()
+                .join("\n");
+            metadata.insert(Self::PREFIXES_KEY.to_string(), prefixes);
+        }
+        if let Some(base_iri) = &self.base_iri {
+            metadata.insert(Self::BASE_KEY.to_string(), base_iri.clone());
+        }
+        metadata
+    }
+
+    /// Expands a prefixed name (e.g., `foaf:name`) into an IRI.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(String)` - The IRI, if the name has a known prefix.
+    /// * `None` - If the name is not a prefixed name or its prefix is unknown.
+    pub fn expand(&self, prefixed_name: &str) -> Option<String> {
+        let (name, local) = prefixed_name.split_once(':')?;
+        let iri = self.prefixes.get(name)?;
+        Some(format!("{}{}", iri, local))
+    }
+}
+
+/// Iterator over the quads of an RDF file that records its namespace declarations.
+///
+/// Prefixes are declared as the document is read, so [`RdfQuadReader::namespaces`] is complete
+/// once the iterator is exhausted.
+pub struct RdfQuadReader {
+    parser: ReaderQuadParser<Box<dyn Read>>,
+}
+
+impl RdfQuadReader {
+    /// Returns the prefixes and base IRI declared so far.
+    pub fn namespaces(&self) -> Namespaces {
+        Namespaces {
+            prefixes: self
+                .parser
+                .prefixes()
+                .map(|(name, iri)| (name.to_string(), iri.to_string()))
+                .collect(),
+            base_iri: self.parser.base_iri().map(str::to_string),
+        }
+    }
+}
+
+impl Iterator for RdfQuadReader {
+    type Item = Result<RdfQuad, CottasError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser
+            .next()
+            .map(|quad_result| Ok(quad_to_tuple(quad_result?)))
+    }
+}
+
+/// Lazily parses an RDF file, yielding one quad at a time.
+///
+/// Unlike [`parse_rdf_file`], the quads are never collected, so arbitrarily large files
+/// can be processed in bounded memory.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the RDF file.
+///
+/// # Returns
+///
+/// * An iterator over subject, predicate, object, and optional graph name tuples.
+///
+/// # Errors
+///
+/// Returns an error if the file extension is unsupported or the file cannot be opened.
+/// Syntax errors are reported by the iterator.
+pub fn parse_rdf_quads(path: &str) -> Result<RdfQuadReader, CottasError> {
+    let format = rdf_format(path)?;
+    Ok(parse_rdf_reader(open_rdf_input(path)?, format))
+}
+
+/// Lazily parses RDF from a reader (e.g., standard input) in the given format.
+///
+/// # Arguments
+///
+/// * `reader` - The uncompressed RDF input.
+/// * `format` - The RDF format of the input.
+///
+/// # Returns
+///
+/// * An iterator over subject, predicate, object, and optional graph name tuples.
+pub fn parse_rdf_reader(reader: impl Read + 'static, format: RdfFormat) -> RdfQuadReader {
+    let reader: Box<dyn Read> = Box::new(reader);
+    RdfQuadReader {
+        parser: RdfParser::from_format(format).for_reader(reader),
+    }
+}
+
+/// Opens an RDF input, decompressing it according to its extension.
+///
+/// Files ending in `.gz`, `.zst`, `.bz2`, or `.xz` are decompressed while they are read
+/// (e.g., `data.nt.gz`), and the path `-` reads from standard input.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the RDF file, or `-`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or the decompressor cannot be created.
+pub fn open_rdf_input(path: &str) -> Result<Box<dyn Read>, CottasError> {
+    let reader: Box<dyn Read> = if path == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+
+    Ok(match Compression::from_path(path) {
+        None => reader,
+        Some(Compression::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(reader)?),
+        Some(Compression::Bzip2) => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Some(Compression::Xz) => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+    })
+}
 
 /// Parses an RDF file and returns its contents as a vector of tuples.
 ///
@@ -17,117 +260,538 @@ use std::io::{BufReader, ErrorKind};
 /// # Returns
 ///
 /// * `Ok(Vec<(String, String, String, Option<String>)>)` - A vector of subject, predicate, object, and optional graph name.
-/// * `Err(Box<dyn Error>)` - If the file cannot be read or parsed.
+/// * `Err(CottasError)` - If the file cannot be read or parsed.
 ///
 /// # Errors
 ///
 /// Returns an error if the file extension is unsupported or if parsing fails.
-pub fn parse_rdf_file(
-    path: &str,
-) -> Result<Vec<(String, String, String, Option<String>)>, Box<dyn Error>> {
-    // 1. Handle unsupported extension error
-    let format_str = extract_format(path).ok_or_else(|| {
-        Box::new(io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unsupported RDF extension for file: {}", path),
-        )) as Box<dyn Error>
-    })?;
-
-    let format = match format_str {
-        "nt" => RdfFormat::NTriples,
-        "nq" => RdfFormat::NQuads,
-        "turtle" => RdfFormat::Turtle,
-        "trig" => RdfFormat::TriG,
-        "xml" => RdfFormat::RdfXml,
-        _ => {
-            return Err(Box::new(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Unsupported RDF format: {}", format_str),
-            )));
-        }
-    };
+pub fn parse_rdf_file(path: &str) -> Result<Vec<RdfQuad>, CottasError> {
+    parse_rdf_quads(path)?.collect()
+}
+
+/// Operation of a data row in an RDF Patch document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOperation {
+    /// `A` row: the quad is added.
+    Add,
+    /// `D` row: the quad is deleted.
+    Delete,
+}
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+/// A data row of an RDF Patch document.
+type PatchRow = (PatchOperation, RdfQuad);
 
-    let parser = RdfParser::from_format(format);
-    let mut quads_vec = Vec::new();
+/// Lazy parser of the data rows of an RDF Patch document.
+///
+/// Rows inside a transaction are buffered until its `TC` row and dropped at its `TA` row, so
+/// the rows of an aborted transaction are never yielded. `PA` and `PD` rows declare and
+/// remove the prefixes used by prefixed names in later rows.
+struct RdfPatchRows<R> {
+    lines: io::Lines<R>,
+    line_number: u64,
+    namespaces: Namespaces,
+    transaction: Option<Vec<PatchRow>>,
+    committed: std::vec::IntoIter<PatchRow>,
+    finished: bool,
+}
 
-    for quad_result in parser.for_reader(reader) {
-        let quad: Quad = quad_result?;
+impl<R: BufRead> RdfPatchRows<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_number: 0,
+            namespaces: Namespaces::default(),
+            transaction: None,
+            committed: Vec::new().into_iter(),
+            finished: false,
+        }
+    }
 
-        let g: Option<String> = match &quad.graph_name {
-            GraphName::NamedNode(ref node) => Some(node.to_string()),
-            GraphName::BlankNode(node) => Some(format!("_:{}", node)),
-            GraphName::DefaultGraph => None,
+    /// Parses a single row, returning the data row it holds, if any.
+    fn parse_row(&mut self, line: &str) -> Result<Option<PatchRow>, CottasError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (code, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let operation = match code {
+            "A" => PatchOperation::Add,
+            "D" => PatchOperation::Delete,
+            "H" => return Ok(None),
+            "TX" => {
+                if self.transaction.is_some() {
+                    return Err(self.syntax_error("Nested RDF Patch transaction"));
+                }
+                self.transaction = Some(Vec::new());
+                return Ok(None);
+            }
+            "TC" | "TA" => {
+                let rows = self.transaction.take().ok_or_else(|| {
+                    self.syntax_error(format!("{} row outside a transaction", code))
+                })?;
+                if code == "TC" {
+                    self.committed = rows.into_iter();
+                }
+                return Ok(None);
+            }
+            "PA" | "PD" => {
+                self.parse_prefix_row(code, rest)?;
+                return Ok(None);
+            }
+            _ => {
+                return Err(self.syntax_error(format!("Unsupported RDF Patch row: {}", line)));
+            }
         };
 
-        quads_vec.push((
-            quad.subject.to_string(),
-            quad.predicate.to_string(),
-            quad.object.to_string(),
-            g,
-        ));
+        let quad = if self.namespaces.prefixes.is_empty() {
+            RdfParser::from_format(RdfFormat::NQuads)
+                .for_reader(rest.as_bytes())
+                .next()
+                .ok_or_else(|| {
+                    self.syntax_error(format!("Missing quad in RDF Patch row: {}", line))
+                })?
+                .map_err(|e| match CottasError::from(e) {
+                    CottasError::Syntax { message, .. } => self.syntax_error(message),
+                    e => e,
+                })?
+        } else {
+            parse_prefixed_quad(rest, &self.namespaces)
+                .map_err(|e| self.syntax_error(e.to_string()))?
+        };
+
+        Ok(Some((operation, quad_to_tuple(quad))))
+    }
+
+    /// Applies a `PA` (prefix add) or `PD` (prefix delete) row.
+    fn parse_prefix_row(&mut self, code: &str, rest: &str) -> Result<(), CottasError> {
+        let rest = rest.trim_end().trim_end_matches('.');
+        let mut fields = rest.split_whitespace();
+        let name = fields
+            .next()
+            .ok_or_else(|| self.syntax_error(format!("Missing prefix in {} row", code)))?
+            .trim_end_matches(':')
+            .to_string();
+
+        if code == "PD" {
+            self.namespaces.prefixes.remove(&name);
+            return Ok(());
+        }
+
+        let iri = fields
+            .next()
+            .and_then(|iri| iri.strip_prefix('<')?.strip_suffix('>'))
+            .ok_or_else(|| self.syntax_error(format!("Missing prefix IRI in PA row: {}", rest)))?;
+        self.namespaces.prefixes.insert(name, iri.to_string());
+        Ok(())
+    }
+
+    /// Builds a syntax error at the current line.
+    fn syntax_error(&self, message: impl Into<String>) -> CottasError {
+        CottasError::syntax_at_line(message, self.line_number)
+    }
+}
+
+impl<R: BufRead> Iterator for RdfPatchRows<R> {
+    type Item = Result<PatchRow, CottasError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.committed.next() {
+                return Some(Ok(row));
+            }
+            if self.finished {
+                return None;
+            }
+
+            let Some(line) = self.lines.next() else {
+                self.finished = true;
+                // A transaction without `TC` is not applied
+                return self
+                    .transaction
+                    .take()
+                    .map(|_| Err(self.syntax_error("Unterminated RDF Patch transaction")));
+            };
+            self.line_number += 1;
+
+            let row = line
+                .map_err(CottasError::from)
+                .and_then(|line| self.parse_row(&line));
+            match row {
+                Ok(Some(row)) => match &mut self.transaction {
+                    Some(rows) => rows.push(row),
+                    None => return Some(Ok(row)),
+                },
+                Ok(None) => {}
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Parses the quad of an RDF Patch row whose terms may be prefixed names.
+fn parse_prefixed_quad(row: &str, namespaces: &Namespaces) -> Result<Quad, CottasError> {
+    let mut terms = tokenize_pattern(row)?;
+    if terms.last() == Some(&".") {
+        terms.pop();
     }
 
-    Ok(quads_vec)
+    let terms = terms
+        .iter()
+        .map(|term| expand_prefixed_term(term, namespaces))
+        .collect::<Result<Vec<_>, _>>()?;
+    match terms.as_slice() {
+        [s, p, o] => parse_quad(s, p, o, None),
+        [s, p, o, g] => parse_quad(s, p, o, Some(g)),
+        _ => Err(CottasError::InvalidTerm(format!(
+            "expected 3 or 4 terms in `{}`",
+            row
+        ))),
+    }
+}
+
+/// Expands a prefixed name, or the prefixed datatype of a literal, into an IRI.
+fn expand_prefixed_term(term: &str, namespaces: &Namespaces) -> Result<String, CottasError> {
+    let expand = |name: &str| {
+        namespaces
+            .expand(name)
+            .ok_or_else(|| CottasError::InvalidTerm(format!("unknown prefix in `{}`", term)))
+    };
+
+    if term.starts_with('"') {
+        match term.rsplit_once("^^") {
+            Some((lexical, datatype)) if lexical.ends_with('"') && !datatype.starts_with('<') => {
+                Ok(format!("{}^^<{}>", lexical, expand(datatype)?))
+            }
+            _ => Ok(term.to_string()),
+        }
+    } else if !term.starts_with(['<', '_']) && term.contains(':') {
+        Ok(format!("<{}>", expand(term)?))
+    } else {
+        Ok(term.to_string())
+    }
+}
+
+/// Lazily parses the data rows of an RDF Patch document.
+///
+/// Rows of aborted transactions (`TA`) are skipped, and prefixed names are resolved with the
+/// prefixes declared by `PA` rows.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the RDF Patch document.
+///
+/// # Returns
+///
+/// * An iterator over the operation and quad of every applied `A` and `D` row, in document
+///   order.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened. Malformed rows and unterminated transactions
+/// are reported by the iterator, as [`CottasError::Syntax`] errors with their line number.
+pub fn parse_rdf_patch(
+    path: &str,
+) -> Result<impl Iterator<Item = Result<(PatchOperation, RdfQuad), CottasError>>, CottasError> {
+    Ok(RdfPatchRows::new(BufReader::new(File::open(path)?)))
+}
+
+/// Builds an [`CottasError::InvalidTerm`] error for a term that cannot be used at a given position.
+fn invalid_term(term: &str, position: &str) -> CottasError {
+    CottasError::InvalidTerm(format!("{} is not a valid {}", term, position))
+}
+
+/// Parses an RDF term written in N-Triples syntax.
+///
+/// # Arguments
+///
+/// * `term` - The term (e.g., `<http://ex/a>`, `_:b0`, or `"chat"@fr`).
+///
+/// # Errors
+///
+/// Returns an error if the term is not valid N-Triples.
+pub fn parse_term(term: &str) -> Result<Term, CottasError> {
+    Ok(Term::from_str(term)?)
+}
+
+/// Parses a quad whose terms are written in N-Triples syntax.
+///
+/// # Arguments
+///
+/// * `s` - The subject, an IRI or a blank node.
+/// * `p` - The predicate, an IRI.
+/// * `o` - The object.
+/// * `g` - The optional graph name, an IRI or a blank node. `None` is the default graph.
+///
+/// # Errors
+///
+/// Returns an error if a term is invalid or not allowed at its position.
+pub fn parse_quad(s: &str, p: &str, o: &str, g: Option<&str>) -> Result<Quad, CottasError> {
+    let predicate = match parse_term(p)? {
+        Term::NamedNode(node) => node,
+        _ => return Err(invalid_term(p, "predicate")),
+    };
+    let object = parse_term(o)?;
+    let graph_name = match g {
+        None => GraphName::DefaultGraph,
+        Some(g) => match parse_term(g)? {
+            Term::NamedNode(node) => node.into(),
+            Term::BlankNode(node) => node.into(),
+            _ => return Err(invalid_term(g, "graph name")),
+        },
+    };
+
+    match parse_term(s)? {
+        Term::NamedNode(node) => Ok(Quad::new(node, predicate, object, graph_name)),
+        Term::BlankNode(node) => Ok(Quad::new(node, predicate, object, graph_name)),
+        _ => Err(invalid_term(s, "subject")),
+    }
 }
 
 /// Position indices for triple/quad patterns
 pub const I_POS: [&str; 4] = ["s", "p", "o", "g"];
 
+/// Returns the end position of the IRI starting at `start`.
+fn iri_end(tp_str: &str, start: usize) -> Result<usize, CottasError> {
+    match tp_str[start..].find('>') {
+        Some(i) => Ok(start + i + 1),
+        None => Err(invalid_pattern(tp_str, "unterminated IRI")),
+    }
+}
+
+/// Returns the end position of the whitespace-delimited word starting at `start`.
+fn word_end(tp_str: &str, start: usize) -> usize {
+    tp_str[start..]
+        .find(char::is_whitespace)
+        .map_or(tp_str.len(), |i| start + i)
+}
+
+/// Returns the end position of the literal starting at `start`, including its language tag or
+/// datatype.
+fn literal_end(tp_str: &str, start: usize) -> Result<usize, CottasError> {
+    let mut chars = tp_str[start..].char_indices().skip(1);
+    let mut end = None;
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => {
+                end = Some(start + i + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let end = end.ok_or_else(|| invalid_pattern(tp_str, "unterminated literal"))?;
+    let suffix = &tp_str[end..];
+
+    if suffix.starts_with('@') {
+        Ok(word_end(tp_str, end))
+    } else if let Some(datatype) = suffix.strip_prefix("^^") {
+        if datatype.starts_with('<') {
+            iri_end(tp_str, end + 2)
+        } else {
+            Ok(word_end(tp_str, end + 2))
+        }
+    } else {
+        Ok(end)
+    }
+}
+
+/// Builds an [`CottasError::InvalidPattern`] error for a malformed triple or quad pattern.
+fn invalid_pattern(tp_str: &str, reason: &str) -> CottasError {
+    CottasError::invalid_pattern(tp_str, reason)
+}
+
+/// Checks whether a term of a triple or quad pattern is a variable (e.g., `?s` or `$s`).
+pub fn is_variable(term: &str) -> bool {
+    term.starts_with('?') || term.starts_with('$')
+}
+
+/// IRI of `rdf:type`, abbreviated as `a` in the predicate position of a pattern.
+pub const RDF_TYPE: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
+
+/// Splits a pattern string into its terms, keeping `.` separators as separate tokens.
+fn tokenize_pattern(tp_str: &str) -> Result<Vec<&str>, CottasError> {
+    let mut terms = Vec::new();
+    let mut pos = 0;
+
+    while let Some(c) = tp_str[pos..].chars().next() {
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+
+        let mut end = match c {
+            '<' => iri_end(tp_str, pos)?,
+            '"' => literal_end(tp_str, pos)?,
+            _ => word_end(tp_str, pos),
+        };
+        // Split a `.` separator glued to the end of a variable or blank node
+        if end - pos > 1 && tp_str[pos..end].ends_with('.') {
+            end -= 1;
+        }
+        terms.push(&tp_str[pos..end]);
+        pos = end;
+    }
+
+    Ok(terms)
+}
+
+/// Validates a variable or normalizes a constant term of a pattern.
+fn normalize_pattern_term(
+    tp_str: &str,
+    term: &str,
+    position: usize,
+    namespaces: &Namespaces,
+) -> Result<String, CottasError> {
+    if is_variable(term) {
+        let name = &term[1..];
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(invalid_pattern(
+                tp_str,
+                &format!("invalid variable `{}`", term),
+            ));
+        }
+        Ok(term.to_string())
+    } else if term == "a" && position == 1 {
+        Ok(RDF_TYPE.to_string())
+    } else {
+        // Prefixed names and prefixed datatypes of literals are expanded first
+        let expanded = expand_prefixed_term(term, namespaces)
+            .map_err(|_| invalid_pattern(tp_str, &format!("unknown prefix in `{}`", term)))?;
+        parse_term(&expanded)
+            .map(|term| term.to_string())
+            .map_err(|e| invalid_pattern(tp_str, &format!("invalid term `{}`: {}", term, e)))
+    }
+}
+
+/// Checks the number of terms of a pattern and normalizes them.
+fn normalize_pattern(
+    tp_str: &str,
+    terms: &[&str],
+    namespaces: &Namespaces,
+) -> Result<Vec<String>, CottasError> {
+    if terms.len() != 3 && terms.len() != 4 {
+        return Err(invalid_pattern(
+            tp_str,
+            &format!("expected 3 or 4 terms, found {}", terms.len()),
+        ));
+    }
+
+    terms
+        .iter()
+        .enumerate()
+        .map(|(position, term)| normalize_pattern_term(tp_str, term, position, namespaces))
+        .collect()
+}
+
 /// Parses a triple or quad pattern string and returns its components.
 ///
+/// Terms are written in N-Triples syntax: IRIs, blank nodes, and literals with optional
+/// language tags or datatypes. Variables start with `?` or `$`, and `a` is accepted as the
+/// predicate `rdf:type`. A trailing `.` is ignored. Constant terms are normalized to the
+/// N-Triples form stored in Cottas files.
+///
 /// # Arguments
 ///
 /// * `tp_str` - The triple or quad pattern as a string (e.g., "?s <http://pred> ?o" or "?s <http://pred> ?o <http://graph>").
 ///
 /// # Returns
 ///
-/// * `Vec<String>` - A vector containing the terms (3 for triple, 4 for quad).
+/// * `Ok(Vec<String>)` - A vector containing the terms (3 for triple, 4 for quad).
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the input string has fewer than 3 terms.
-pub fn parse_tp(tp_str: &str) -> Vec<String> {
-    let parts: Vec<&str> = tp_str.split_whitespace().collect();
+/// Returns an error if a term is malformed or the pattern does not have 3 or 4 terms.
+pub fn parse_tp(tp_str: &str) -> Result<Vec<String>, CottasError> {
+    parse_tp_with_namespaces(tp_str, &Namespaces::default())
+}
 
-    if parts.len() < 3 {
-        panic!("Triple pattern must have at least 3 terms");
+/// Parses a triple or quad pattern string, resolving prefixed names against `namespaces`.
+///
+/// Besides the syntax accepted by [`parse_tp`], IRIs may be written as prefixed names
+/// (e.g., "?s foaf:name ?o").
+///
+/// # Arguments
+///
+/// * `tp_str` - The triple or quad pattern as a string.
+/// * `namespaces` - The prefixes used to expand prefixed names.
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - A vector containing the terms (3 for triple, 4 for quad).
+///
+/// # Errors
+///
+/// Returns an error if a term is malformed, a prefix is unknown, or the pattern does not
+/// have 3 or 4 terms.
+pub fn parse_tp_with_namespaces(
+    tp_str: &str,
+    namespaces: &Namespaces,
+) -> Result<Vec<String>, CottasError> {
+    let mut terms = tokenize_pattern(tp_str)?;
+
+    if terms.last() == Some(&".") {
+        terms.pop();
     }
 
-    let s_term = parts[0].to_string();
-    let p_term = parts[1].to_string();
+    normalize_pattern(tp_str, &terms, namespaces)
+}
+
+/// Parses a basic graph pattern, a set of triple or quad patterns separated by `.`.
+///
+/// # Arguments
+///
+/// * `bgp_str` - The basic graph pattern (e.g., "?p a <http://ex/Person> . ?p <http://ex/name> ?n").
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<String>>)` - The terms of every pattern, as returned by [`parse_tp`].
+///
+/// # Errors
+///
+/// Returns an error if a pattern is malformed or the basic graph pattern is empty.
+pub fn parse_bgp(bgp_str: &str) -> Result<Vec<Vec<String>>, CottasError> {
+    parse_bgp_with_namespaces(bgp_str, &Namespaces::default())
+}
 
-    // Object might contain spaces, so reconstruct it
-    let after_predicate = tp_str
-        .replacen(&s_term, "", 1)
-        .replacen(&p_term, "", 1)
-        .trim()
-        .to_string();
+/// Parses a basic graph pattern, resolving prefixed names against the given namespaces.
+///
+/// # Arguments
+///
+/// * `bgp_str` - The basic graph pattern (e.g., "?p a ex:Person . ?p ex:name ?n").
+/// * `namespaces` - The prefixes used to expand prefixed names.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<String>>)` - The terms of every pattern, as returned by [`parse_tp`].
+///
+/// # Errors
+///
+/// Returns an error if a pattern is malformed, a prefix is unknown, or the basic graph
+/// pattern is empty.
+pub fn parse_bgp_with_namespaces(
+    bgp_str: &str,
+    namespaces: &Namespaces,
+) -> Result<Vec<Vec<String>>, CottasError> {
+    let terms = tokenize_pattern(bgp_str)?;
 
-    // Check if there's a graph term (quad pattern)
-    let (o_term, g_term_opt) = if parts.len() > 3 {
-        let last_token = parts[parts.len() - 1];
-        if last_token.starts_with('<') || last_token.starts_with('?') {
-            // It's a quad - last token is graph
-            let o = after_predicate
-                .replacen(last_token, "", 1)
-                .trim()
-                .to_string();
-            (o, Some(last_token.to_string()))
-        } else {
-            (after_predicate, None)
-        }
-    } else {
-        (after_predicate, None)
-    };
+    let patterns = terms
+        .split(|term| *term == ".")
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| normalize_pattern(bgp_str, pattern, namespaces))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    // Build result vector
-    let mut result = vec![s_term, p_term, o_term];
-    if let Some(g_term) = g_term_opt {
-        result.push(g_term);
+    if patterns.is_empty() {
+        return Err(invalid_pattern(bgp_str, "no triple patterns"));
     }
 
-    result
+    Ok(patterns)
 }
@@ -0,0 +1,666 @@
+//! Read-only HTTP interfaces over Cottas files: a SPARQL 1.1 Protocol endpoint and a Triple
+//! Pattern Fragments server.
+
+use crate::duckdb::connection_in_memory;
+use crate::error::CottasError;
+use crate::file::CottasFile;
+use crate::parser::{parse_quad, parse_term};
+use crate::sparql::{evaluate_query, parse_query, write_query_results_with_rdf_format};
+use oxrdf::vocab::{rdf, xsd};
+use oxrdf::{BlankNode, Literal, NamedNode, NamedOrBlankNode, Quad, Term};
+use oxrdfio::{RdfFormat, RdfSerializer};
+use sparesults::QueryResultsFormat;
+use spargebra::Query;
+use std::io::{self, Read};
+use std::num::NonZeroUsize;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Path of the SPARQL endpoint.
+pub const SPARQL_PATH: &str = "/sparql";
+
+/// Maximum size in bytes of the body of a POST request.
+pub const MAX_QUERY_BYTES: u64 = 1024 * 1024;
+
+/// Chooses the SPARQL results format for an `Accept` header.
+///
+/// Media ranges are tried in decreasing order of quality, and JSON is used when the header is
+/// missing or accepts any type.
+///
+/// # Arguments
+///
+/// * `accept` - The value of the `Accept` header, if any.
+///
+/// # Returns
+///
+/// * `Some(QueryResultsFormat)` - The preferred supported format.
+/// * `None` - If none of the accepted media types is supported.
+pub fn negotiate_results_format(accept: Option<&str>) -> Option<QueryResultsFormat> {
+    let Some(accept) = accept else {
+        return Some(QueryResultsFormat::Json);
+    };
+
+    media_ranges(accept)
+        .into_iter()
+        .find_map(|media_type| match media_type {
+            "*/*" | "application/*" => Some(QueryResultsFormat::Json),
+            "text/*" => Some(QueryResultsFormat::Csv),
+            _ => QueryResultsFormat::from_media_type(media_type),
+        })
+}
+
+/// Chooses the RDF format of Triple Pattern Fragments for an `Accept` header.
+///
+/// Only formats that can hold the separate metadata graph are offered, and TriG is used when
+/// the header is missing or accepts any type.
+///
+/// # Arguments
+///
+/// * `accept` - The value of the `Accept` header, if any.
+///
+/// # Returns
+///
+/// * `Some(RdfFormat)` - The preferred supported format.
+/// * `None` - If none of the accepted media types is supported.
+pub fn negotiate_rdf_format(accept: Option<&str>) -> Option<RdfFormat> {
+    let Some(accept) = accept else {
+        return Some(RdfFormat::TriG);
+    };
+
+    media_ranges(accept)
+        .into_iter()
+        .find_map(|media_type| match media_type {
+            "*/*" | "application/*" => Some(RdfFormat::TriG),
+            _ => RdfFormat::from_media_type(media_type)
+                .filter(|f| matches!(f, RdfFormat::TriG | RdfFormat::NQuads)),
+        })
+}
+
+/// Chooses the RDF format of the triples of CONSTRUCT queries for an `Accept` header.
+///
+/// Media ranges are tried in decreasing order of quality, and N-Triples is used when the header
+/// is missing or accepts any type.
+///
+/// # Arguments
+///
+/// * `accept` - The value of the `Accept` header, if any.
+///
+/// # Returns
+///
+/// * `Some(RdfFormat)` - The preferred supported format.
+/// * `None` - If none of the accepted media types is supported.
+pub fn negotiate_graph_format(accept: Option<&str>) -> Option<RdfFormat> {
+    let Some(accept) = accept else {
+        return Some(RdfFormat::NTriples);
+    };
+
+    media_ranges(accept)
+        .into_iter()
+        .find_map(|media_type| match media_type {
+            "*/*" | "application/*" => Some(RdfFormat::NTriples),
+            "text/*" => Some(RdfFormat::Turtle),
+            _ => RdfFormat::from_media_type(media_type),
+        })
+}
+
+/// Returns the media ranges of an `Accept` header in decreasing order of quality.
+fn media_ranges(accept: &str) -> Vec<&str> {
+    let mut ranges: Vec<(&str, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().filter(|m| !m.is_empty())?;
+            let quality = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect();
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges
+        .into_iter()
+        .map(|(media_type, _)| media_type)
+        .collect()
+}
+
+/// Extracts the value of a parameter from an `application/x-www-form-urlencoded` string.
+fn form_parameter(form: &str, name: &str) -> Option<String> {
+    form_urlencoded::parse(form.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Returns the value of a request header.
+fn header_value<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Builds a plain text response with the given status code.
+fn text_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(message)
+        .with_status_code(status)
+        .with_header(content_type("text/plain; charset=utf-8"))
+}
+
+/// Builds a `Content-Type` header.
+fn content_type(media_type: &str) -> Header {
+    Header::from_bytes("Content-Type", media_type).expect("valid header")
+}
+
+/// Builds the header allowing browser-based clients from any origin.
+fn allow_any_origin() -> Header {
+    Header::from_bytes("Access-Control-Allow-Origin", "*").expect("valid header")
+}
+
+/// Extracts the query string of a SPARQL Protocol request.
+///
+/// Queries are accepted via GET, via POST with a form body, and via POST with a
+/// `application/sparql-query` body.
+///
+/// # Errors
+///
+/// Returns the status code and message of the error response for invalid requests, including
+/// 413 for bodies larger than [`MAX_QUERY_BYTES`].
+fn read_query(request: &mut Request) -> Result<String, (u16, String)> {
+    let url = request.url().to_string();
+    let (path, parameters) = url.split_once('?').unwrap_or((url.as_str(), ""));
+    if path != SPARQL_PATH {
+        return Err((
+            404,
+            format!("Not found. The endpoint is at {}.", SPARQL_PATH),
+        ));
+    }
+
+    let form = match request.method() {
+        Method::Get => parameters.to_string(),
+        Method::Post => {
+            let media_type = header_value(request, "Content-Type")
+                .and_then(|v| v.split(';').next())
+                .map(|v| v.trim().to_ascii_lowercase())
+                .unwrap_or_default();
+            let too_large = || {
+                (
+                    413,
+                    format!("The request body exceeds {} bytes.", MAX_QUERY_BYTES),
+                )
+            };
+            if request
+                .body_length()
+                .is_some_and(|length| length as u64 > MAX_QUERY_BYTES)
+            {
+                return Err(too_large());
+            }
+            // Bodies without a Content-Length are read up to one byte past the limit
+            let mut body = String::new();
+            request
+                .as_reader()
+                .take(MAX_QUERY_BYTES + 1)
+                .read_to_string(&mut body)
+                .map_err(|e| (400, e.to_string()))?;
+            if body.len() as u64 > MAX_QUERY_BYTES {
+                return Err(too_large());
+            }
+            match media_type.as_str() {
+                "application/sparql-query" => return Ok(body),
+                "application/x-www-form-urlencoded" => body,
+                "application/sparql-update" => {
+                    return Err((403, "The endpoint is read-only.".to_string()))
+                }
+                other => return Err((415, format!("Unsupported content type `{}`.", other))),
+            }
+        }
+        _ => return Err((405, "Only GET and POST are allowed.".to_string())),
+    };
+
+    if form_parameter(&form, "update").is_some() {
+        return Err((403, "The endpoint is read-only.".to_string()));
+    }
+    if form_parameter(&form, "default-graph-uri").is_some()
+        || form_parameter(&form, "named-graph-uri").is_some()
+    {
+        return Err((400, "Dataset parameters are not supported.".to_string()));
+    }
+    form_parameter(&form, "query").ok_or_else(|| (400, "Missing `query` parameter.".to_string()))
+}
+
+/// Answers a single SPARQL Protocol request.
+fn handle_request(cottas_file_paths: &[String], mut request: Request) -> std::io::Result<()> {
+    let query = match read_query(&mut request) {
+        Ok(query) => query,
+        Err((status, message)) => return request.respond(text_response(status, &message)),
+    };
+
+    let conn = match connection_in_memory() {
+        Ok(conn) => conn,
+        Err(e) => return request.respond(text_response(500, &e.to_string())),
+    };
+    let query = match parse_query(&conn, cottas_file_paths, &query) {
+        Ok(query) => query,
+        Err(e @ CottasError::InvalidQuery(_)) => {
+            return request.respond(text_response(400, &e.to_string()))
+        }
+        Err(e) => return request.respond(text_response(500, &e.to_string())),
+    };
+
+    // The format is negotiated before evaluation, and only the one matching the query form is
+    // used when writing the results.
+    let accept = header_value(&request, "Accept");
+    let negotiated = match query {
+        Query::Construct { .. } | Query::Describe { .. } => negotiate_graph_format(accept)
+            .map(|format| (QueryResultsFormat::Json, format, format.media_type())),
+        Query::Select { .. } | Query::Ask { .. } => negotiate_results_format(accept)
+            .map(|format| (format, RdfFormat::NTriples, format.media_type())),
+    };
+    let Some((format, rdf_format, media_type)) = negotiated else {
+        return request.respond(text_response(
+            406,
+            "None of the accepted media types is supported.",
+        ));
+    };
+
+    let results = match evaluate_query(&conn, cottas_file_paths, query) {
+        Ok(results) => results,
+        Err(e @ (CottasError::InvalidQuery(_) | CottasError::Unsupported(_))) => {
+            return request.respond(text_response(400, &e.to_string()))
+        }
+        Err(e) => return request.respond(text_response(500, &e.to_string())),
+    };
+
+    let mut body = Vec::new();
+    if let Err(e) = write_query_results_with_rdf_format(&results, format, rdf_format, &mut body) {
+        return request.respond(text_response(500, &e.to_string()));
+    }
+
+    let response = Response::from_data(body)
+        .with_header(content_type(media_type))
+        .with_header(allow_any_origin());
+    request.respond(response)
+}
+
+/// Serves one or more Cottas files as a read-only SPARQL 1.1 Protocol endpoint.
+///
+/// Queries are answered at [`SPARQL_PATH`] like [`query_in_duckdb`](crate::query_in_duckdb),
+/// and the format is negotiated with the `Accept` header before the query is evaluated: SPARQL
+/// results formats for SELECT and ASK, and RDF formats for CONSTRUCT. This function blocks and
+/// answers requests on one thread per available CPU; failures to answer a request are reported
+/// on standard error.
+///
+/// # Arguments
+///
+/// * `cottas_file_paths` - Paths to the Parquet files served as a single dataset.
+/// * `address` - Socket address to listen on (e.g., "127.0.0.1:7878").
+///
+/// # Errors
+///
+/// Returns an error if the address cannot be bound.
+pub fn serve(cottas_file_paths: &[String], address: &str) -> Result<(), CottasError> {
+    let server = Server::http(address).map_err(|e| CottasError::Io(io::Error::other(e)))?;
+
+    answer_requests(&server, cottas_file_paths, |paths, request| {
+        handle_request(paths, request)
+    });
+
+    Ok(())
+}
+
+/// Answers the requests of a server on one thread per available CPU.
+///
+/// Every thread owns a clone of `state`, which `handle` receives with each request.
+fn answer_requests<S, F>(server: &Server, state: S, handle: F)
+where
+    S: Clone + Send,
+    F: Fn(&S, Request) -> io::Result<()> + Sync,
+{
+    let workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let state = state.clone();
+            let handle = &handle;
+            scope.spawn(move || {
+                for request in server.incoming_requests() {
+                    if let Err(e) = handle(&state, request) {
+                        eprintln!("Failed to answer request: {}", e);
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Path of the Triple Pattern Fragments interface.
+pub const TPF_PATH: &str = "/fragments";
+
+/// Number of triples or quads per Triple Pattern Fragments page.
+pub const TPF_PAGE_SIZE: usize = 100;
+
+/// Names of the selector parameters of Triple Pattern Fragments, in pattern order.
+const TPF_PARAMETERS: [&str; 4] = ["s", "p", "o", "g"];
+
+const HYDRA: &str = "http://www.w3.org/ns/hydra/core#";
+const VOID: &str = "http://rdfs.org/ns/void#";
+const SD_GRAPH: &str = "http://www.w3.org/ns/sparql-service-description#graph";
+
+/// Converts a selector value in `hydra:ExplicitRepresentation` to an N-Triples term.
+///
+/// Empty values, variables, and blank nodes leave the position unbound and return `None`.
+/// N-Triples syntax (e.g., `<http://ex/a>`) is accepted as well.
+fn selector_term(value: &str) -> Result<Option<String>, CottasError> {
+    let value = value.trim();
+    if value.is_empty() || value.starts_with('?') || value.starts_with("_:") {
+        return Ok(None);
+    }
+
+    let term = if let Some(literal) = value.strip_prefix('"') {
+        let end = literal
+            .rfind('"')
+            .ok_or_else(|| CottasError::InvalidTerm(format!("`{}` is not a literal", value)))?;
+        let (lexical, suffix) = (&literal[..end], &literal[end + 1..]);
+        let literal = if let Some(language) = suffix.strip_prefix('@') {
+            Literal::new_language_tagged_literal(lexical, language)?
+        } else if let Some(datatype) = suffix.strip_prefix("^^") {
+            let datatype = datatype.trim_start_matches('<').trim_end_matches('>');
+            Literal::new_typed_literal(lexical, NamedNode::new(datatype)?)
+        } else if suffix.is_empty() {
+            Literal::new_simple_literal(lexical)
+        } else {
+            return Err(CottasError::InvalidTerm(format!(
+                "`{}` is not a literal",
+                value
+            )));
+        };
+        Term::from(literal)
+    } else if value.starts_with('<') {
+        parse_term(value)?
+    } else {
+        Term::from(NamedNode::new(value)?)
+    };
+
+    Ok(Some(term.to_string()))
+}
+
+/// Builds the URL of a fragment page, keeping only the bound selectors.
+fn fragment_url(base: &str, selectors: &[(&str, String)], page: usize) -> String {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for (name, value) in selectors {
+        query.append_pair(name, value);
+    }
+    if page > 1 {
+        query.append_pair("page", &page.to_string());
+    }
+    let query = query.finish();
+
+    if query.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, query)
+    }
+}
+
+/// Builds a Triple Pattern Fragments page: the matching quads followed by the count metadata
+/// and hypermedia controls in the `#metadata` graph of the page.
+fn build_fragment(
+    dataset: &CottasFile,
+    base: &str,
+    form: &str,
+) -> Result<Vec<Quad>, (u16, String)> {
+    let bad_request = |e: CottasError| (400, e.to_string());
+    let server_error = |e: CottasError| (500, e.to_string());
+
+    let page = match form_parameter(form, "page") {
+        Some(page) => page
+            .parse::<usize>()
+            .ok()
+            .filter(|page| *page > 0)
+            .ok_or_else(|| (400, format!("Invalid page `{}`.", page)))?,
+        None => 1,
+    };
+
+    let mut selectors = Vec::new();
+    let mut terms = Vec::with_capacity(TPF_PARAMETERS.len());
+    for name in TPF_PARAMETERS {
+        let value = form_parameter(form, name).unwrap_or_default();
+        let term = selector_term(&value).map_err(bad_request)?;
+        if term.is_some() {
+            selectors.push((name, value));
+        }
+        terms.push(term);
+    }
+
+    // Without a graph selector, quads are matched across all graphs as triples
+    let positions = if terms[3].is_some() { 4 } else { 3 };
+    let pattern = (0..positions)
+        .map(|i| {
+            terms[i]
+                .clone()
+                .unwrap_or_else(|| format!("?{}", TPF_PARAMETERS[i]))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let offset = (page - 1) * TPF_PAGE_SIZE;
+    let (mut rows, estimate) = if positions == 4 && !dataset.quad_mode() {
+        (Vec::new(), 0)
+    } else {
+        let rows = dataset
+            .page(&pattern, offset as u64, (TPF_PAGE_SIZE + 1) as u64)
+            .map_err(server_error)?;
+        let estimate = dataset.estimate_count(&pattern).map_err(server_error)?;
+        (rows, estimate)
+    };
+
+    // The count is exact on the last page, and the metadata estimate elsewhere
+    let has_next = rows.len() > TPF_PAGE_SIZE;
+    rows.truncate(TPF_PAGE_SIZE);
+    let count = if has_next {
+        estimate.max((offset + TPF_PAGE_SIZE + 1) as u64)
+    } else if rows.is_empty() && offset > 0 {
+        // The page is past the last match
+        dataset.page_count(&pattern).map_err(server_error)?
+    } else {
+        (offset + rows.len()) as u64
+    };
+
+    let mut quads = Vec::with_capacity(rows.len() + 32);
+    for row in &rows {
+        let quad = parse_quad(&row[0], &row[1], &row[2], row.get(3).map(String::as_str))
+            .map_err(server_error)?;
+        quads.push(quad);
+    }
+
+    let iri = |iri: String| NamedNode::new(iri).map_err(|e| (400, e.to_string()));
+    let vocab =
+        |namespace: &str, name: &str| NamedNode::new_unchecked(format!("{}{}", namespace, name));
+    let integer = |n: u64| Term::from(Literal::new_typed_literal(n.to_string(), xsd::INTEGER));
+
+    let fragment = iri(fragment_url(base, &selectors, 1))?;
+    let page_node = iri(fragment_url(base, &selectors, page))?;
+    let dataset_node = iri(format!("{}#dataset", base))?;
+    let metadata = iri(format!("{}#metadata", page_node.as_str()))?;
+
+    let mut add = |subject: NamedOrBlankNode, predicate: NamedNode, object: Term| {
+        quads.push(Quad::new(subject, predicate, object, metadata.clone()));
+    };
+
+    add(
+        fragment.clone().into(),
+        rdf::TYPE.into(),
+        vocab(HYDRA, "Collection").into(),
+    );
+    add(
+        fragment.clone().into(),
+        vocab(VOID, "triples"),
+        integer(count),
+    );
+    add(
+        fragment.clone().into(),
+        vocab(HYDRA, "totalItems"),
+        integer(count),
+    );
+    add(
+        fragment.clone().into(),
+        vocab(HYDRA, "itemsPerPage"),
+        integer(TPF_PAGE_SIZE as u64),
+    );
+    add(
+        fragment.clone().into(),
+        vocab(HYDRA, "view"),
+        page_node.clone().into(),
+    );
+
+    add(
+        page_node.clone().into(),
+        rdf::TYPE.into(),
+        vocab(HYDRA, "PartialCollectionView").into(),
+    );
+    add(
+        page_node.clone().into(),
+        vocab(HYDRA, "first"),
+        fragment.clone().into(),
+    );
+    if page > 1 {
+        let previous = iri(fragment_url(base, &selectors, page - 1))?;
+        add(
+            page_node.clone().into(),
+            vocab(HYDRA, "previous"),
+            previous.into(),
+        );
+    }
+    if has_next {
+        let next = iri(fragment_url(base, &selectors, page + 1))?;
+        add(page_node.clone().into(), vocab(HYDRA, "next"), next.into());
+    }
+
+    let search = BlankNode::default();
+    add(
+        dataset_node.clone().into(),
+        rdf::TYPE.into(),
+        vocab(VOID, "Dataset").into(),
+    );
+    add(
+        dataset_node.clone().into(),
+        rdf::TYPE.into(),
+        vocab(HYDRA, "Collection").into(),
+    );
+    add(
+        dataset_node.clone().into(),
+        vocab(VOID, "subset"),
+        fragment.into(),
+    );
+    add(
+        dataset_node.into(),
+        vocab(HYDRA, "search"),
+        search.clone().into(),
+    );
+    add(
+        search.clone().into(),
+        vocab(HYDRA, "template"),
+        Literal::new_simple_literal(format!("{}{{?{}}}", base, TPF_PARAMETERS.join(","))).into(),
+    );
+    add(
+        search.clone().into(),
+        vocab(HYDRA, "variableRepresentation"),
+        vocab(HYDRA, "ExplicitRepresentation").into(),
+    );
+
+    let properties = [
+        rdf::SUBJECT.into_owned(),
+        rdf::PREDICATE.into_owned(),
+        rdf::OBJECT.into_owned(),
+        NamedNode::new_unchecked(SD_GRAPH),
+    ];
+    for (name, property) in TPF_PARAMETERS.into_iter().zip(properties) {
+        let mapping = BlankNode::default();
+        add(
+            search.clone().into(),
+            vocab(HYDRA, "mapping"),
+            mapping.clone().into(),
+        );
+        add(
+            mapping.clone().into(),
+            vocab(HYDRA, "variable"),
+            Literal::new_simple_literal(name).into(),
+        );
+        add(mapping.into(), vocab(HYDRA, "property"), property.into());
+    }
+
+    Ok(quads)
+}
+
+/// Answers a single Triple Pattern Fragments request.
+fn handle_tpf_request(
+    dataset: &CottasFile,
+    address: &str,
+    request: Request,
+) -> std::io::Result<()> {
+    let url = request.url().to_string();
+    let (path, form) = url.split_once('?').unwrap_or((url.as_str(), ""));
+    if path != TPF_PATH {
+        let message = format!("Not found. The fragments are at {}.", TPF_PATH);
+        return request.respond(text_response(404, &message));
+    }
+    if request.method() != &Method::Get {
+        return request.respond(text_response(405, "Only GET is allowed."));
+    }
+
+    let Some(format) = negotiate_rdf_format(header_value(&request, "Accept")) else {
+        return request.respond(text_response(
+            406,
+            "None of the accepted media types is supported.",
+        ));
+    };
+
+    let host = header_value(&request, "Host").unwrap_or(address);
+    let base = format!("http://{}{}", host, TPF_PATH);
+    let quads = match build_fragment(dataset, &base, form) {
+        Ok(quads) => quads,
+        Err((status, message)) => return request.respond(text_response(status, &message)),
+    };
+
+    let mut serializer = RdfSerializer::from_format(format).for_writer(Vec::new());
+    for quad in &quads {
+        serializer.serialize_quad(quad)?;
+    }
+    let body = serializer.finish()?;
+
+    let response = Response::from_data(body)
+        .with_header(content_type(format.media_type()))
+        .with_header(allow_any_origin());
+    request.respond(response)
+}
+
+/// Serves a Cottas file as Triple Pattern Fragments.
+///
+/// Fragments are answered at [`TPF_PATH`] with the `s`, `p`, `o`, and `g` selectors using
+/// [`CottasFile::page`], in pages of [`TPF_PAGE_SIZE`] quads sorted by the index of the file.
+/// Each page carries a count estimated from the Parquet metadata (see
+/// [`CottasFile::estimate_count`]) and the Hydra controls to navigate pages and build new
+/// fragments. This function blocks and answers requests on one thread per available CPU, each
+/// with its own clone of the [`CottasFile`]; failures to answer a request are reported on
+/// standard error.
+///
+/// # Arguments
+///
+/// * `cottas_file_path` - Path to the Parquet file.
+/// * `address` - Socket address to listen on (e.g., "127.0.0.1:7878").
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or the address cannot be bound.
+pub fn serve_tpf(cottas_file_path: &str, address: &str) -> Result<(), CottasError> {
+    let dataset = CottasFile::open(cottas_file_path)?;
+
+    let server = Server::http(address).map_err(|e| CottasError::Io(io::Error::other(e)))?;
+
+    answer_requests(&server, dataset, |dataset, request| {
+        handle_tpf_request(dataset, address, request)
+    });
+
+    Ok(())
+}
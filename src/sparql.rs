@@ -0,0 +1,1423 @@
+//! SPARQL query evaluation over Cottas files by translating the SPARQL algebra into DuckDB SQL.
+
+use crate::duckdb::{connection_in_memory, has_column, read_namespaces};
+use crate::error::CottasError;
+use crate::parser::{parse_quad, parse_term};
+use crate::utils::{sql_identifier, sql_string};
+use duckdb::Connection;
+use oxrdf::{BlankNode, Term, Triple, Variable};
+use oxrdfio::{RdfFormat, RdfSerializer};
+use sparesults::{QueryResultsFormat, QueryResultsSerializer};
+use spargebra::algebra::{
+    AggregateExpression, AggregateFunction, Expression, Function, GraphPattern, OrderExpression,
+};
+use spargebra::term::{NamedNodePattern, TermPattern, TriplePattern};
+use spargebra::{Query, SparqlParser};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+/// XSD namespace, used by the SQL macros and the typed literals built from SQL values.
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Results of a SPARQL query.
+#[derive(Debug)]
+pub enum QueryResults {
+    /// Solutions of a SELECT query, with one optional term per variable.
+    Solutions {
+        /// The projected variables.
+        variables: Vec<Variable>,
+        /// The solutions, with terms in the order of `variables`.
+        solutions: Vec<Vec<Option<Term>>>,
+    },
+    /// Result of an ASK query.
+    Boolean(bool),
+    /// Triples built by a CONSTRUCT query.
+    Graph(Vec<Triple>),
+}
+
+/// Creates the `sparql_*` macros used by the generated SQL.
+///
+/// * `sparql_unescape(s)` - Decodes the N-Triples escape sequences of a string.
+/// * `sparql_lex(t)` - Lexical form of a literal, or the IRI of a named node.
+/// * `sparql_kind(t)` - Sorts blank nodes before IRIs, and IRIs before literals.
+/// * `sparql_datatype(t)` - Datatype IRI of a literal.
+/// * `sparql_lang(t)` - Language tag of a literal, or an empty string.
+/// * `sparql_num(t)` - Numeric value of a numeric literal as a `DOUBLE`.
+/// * `sparql_ebv(t)` - Effective boolean value of a term.
+/// * `sparql_eq(a, b)` / `sparql_cmp(a, b)` - Value equality and ordering of two terms.
+/// * `sparql_num_term(x)` / `sparql_str_term(s)` / `sparql_bool_term(b)` - Builds literals.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+pub fn create_sparql_macros(conn: &Connection) -> Result<(), CottasError> {
+    conn.execute_batch(&format!(
+        r#"
+        CREATE TEMP MACRO sparql_unescape_hex(s, escape, digits) AS array_to_string(
+            list_transform(string_split(s, escape), lambda part, i: CASE
+                WHEN i = 1 THEN part
+                ELSE chr(CAST('0x' || part[1:digits] AS INTEGER)) || part[digits + 1:]
+            END), '');
+        CREATE TEMP MACRO sparql_unescape(s) AS replace(replace(replace(replace(replace(replace(
+            replace(replace(sparql_unescape_hex(sparql_unescape_hex(
+                replace(s, '\\', chr(0)), '\U', 8), '\u', 4),
+            '\t', chr(9)), '\b', chr(8)), '\n', chr(10)), '\r', chr(13)), '\f', chr(12)),
+            '\"', '"'), '\''', ''''), chr(0), '\');
+        CREATE TEMP MACRO sparql_lex(t) AS CASE
+            WHEN starts_with(t, '"') THEN
+                sparql_unescape(regexp_extract(t, '^"(.*)"(@[^"]*|\^\^<[^>]*>)?$', 1))
+            WHEN starts_with(t, '<') THEN t[2:-2]
+            ELSE t
+        END;
+        CREATE TEMP MACRO sparql_kind(t) AS CASE
+            WHEN starts_with(t, '_:') THEN 0
+            WHEN starts_with(t, '<') THEN 1
+            WHEN t IS NOT NULL THEN 2
+        END;
+        CREATE TEMP MACRO sparql_datatype(t) AS CASE
+            WHEN regexp_matches(t, '^".*"\^\^<[^>]*>$') THEN regexp_extract(t, '\^\^<([^>]*)>$', 1)
+            WHEN regexp_matches(t, '^".*"@[^"]*$') THEN 'http://www.w3.org/1999/02/22-rdf-syntax-ns#langString'
+            WHEN starts_with(t, '"') THEN '{xsd}string'
+        END;
+        CREATE TEMP MACRO sparql_lang(t) AS CASE
+            WHEN regexp_matches(t, '^".*"@[^"]*$') THEN regexp_extract(t, '"@([^"]*)$', 1)
+            ELSE ''
+        END;
+        CREATE TEMP MACRO sparql_num(t) AS CASE
+            WHEN sparql_datatype(t) IN ('{xsd}integer', '{xsd}decimal', '{xsd}double', '{xsd}float',
+                '{xsd}int', '{xsd}long', '{xsd}short', '{xsd}byte', '{xsd}nonNegativeInteger',
+                '{xsd}positiveInteger', '{xsd}negativeInteger', '{xsd}nonPositiveInteger',
+                '{xsd}unsignedInt', '{xsd}unsignedLong', '{xsd}unsignedShort', '{xsd}unsignedByte')
+            THEN TRY_CAST(sparql_lex(t) AS DOUBLE)
+        END;
+        CREATE TEMP MACRO sparql_ebv(t) AS CASE
+            WHEN sparql_datatype(t) = '{xsd}boolean' THEN sparql_lex(t) IN ('true', '1')
+            WHEN sparql_num(t) IS NOT NULL THEN sparql_num(t) <> 0
+            WHEN starts_with(t, '"') THEN length(sparql_lex(t)) > 0
+        END;
+        CREATE TEMP MACRO sparql_eq(a, b) AS CASE
+            WHEN sparql_num(a) IS NOT NULL AND sparql_num(b) IS NOT NULL
+            THEN sparql_num(a) = sparql_num(b)
+            ELSE a = b
+        END;
+        CREATE TEMP MACRO sparql_cmp(a, b) AS CASE
+            WHEN a IS NULL OR b IS NULL THEN NULL
+            WHEN sparql_num(a) IS NOT NULL AND sparql_num(b) IS NOT NULL
+            THEN sign(sparql_num(a) - sparql_num(b))
+            WHEN sparql_lex(a) < sparql_lex(b) THEN -1
+            WHEN sparql_lex(a) > sparql_lex(b) THEN 1
+            ELSE 0
+        END;
+        CREATE TEMP MACRO sparql_num_term(x) AS CASE
+            WHEN x = trunc(x) AND abs(x) < 1e18
+            THEN '"' || CAST(CAST(x AS BIGINT) AS VARCHAR) || '"^^<{xsd}integer>'
+            WHEN x IS NOT NULL THEN '"' || CAST(x AS VARCHAR) || '"^^<{xsd}double>'
+        END;
+        CREATE TEMP MACRO sparql_str_term(s) AS
+            '"' || replace(replace(replace(replace(s, '\', '\\'), '"', '\"'), chr(10), '\n'), chr(13), '\r') || '"';
+        CREATE TEMP MACRO sparql_bool_term(b) AS CASE
+            WHEN b THEN '"true"^^<{xsd}boolean>'
+            WHEN NOT b THEN '"false"^^<{xsd}boolean>'
+        END;
+        "#,
+        xsd = XSD
+    ))?;
+    Ok(())
+}
+
+/// Builds the error returned for SPARQL features without an SQL translation.
+fn unsupported(feature: &str) -> CottasError {
+    CottasError::Unsupported(format!("SPARQL feature {}", feature))
+}
+
+/// Kind of value produced by a translated expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// An RDF term in N-Triples syntax.
+    Term,
+    /// An SQL boolean.
+    Boolean,
+    /// An SQL `DOUBLE`.
+    Numeric,
+    /// An SQL string holding the lexical form of a simple literal.
+    String,
+}
+
+/// An SQL expression and the kind of value it produces.
+#[derive(Debug, Clone)]
+struct SqlExpression {
+    sql: String,
+    kind: Kind,
+}
+
+impl SqlExpression {
+    fn new(sql: String, kind: Kind) -> Self {
+        Self { sql, kind }
+    }
+
+    /// Converts the value to an RDF term in N-Triples syntax.
+    fn term(self) -> String {
+        match self.kind {
+            Kind::Term => self.sql,
+            Kind::Boolean => format!("sparql_bool_term({})", self.sql),
+            Kind::Numeric => format!("sparql_num_term({})", self.sql),
+            Kind::String => format!("sparql_str_term({})", self.sql),
+        }
+    }
+
+    /// Converts the value to its effective boolean value.
+    fn boolean(self) -> String {
+        match self.kind {
+            Kind::Term => format!("sparql_ebv({})", self.sql),
+            Kind::Boolean => self.sql,
+            Kind::Numeric => format!("(({}) <> 0)", self.sql),
+            Kind::String => format!("(length({}) > 0)", self.sql),
+        }
+    }
+
+    /// Converts the value to a number.
+    fn numeric(self) -> String {
+        match self.kind {
+            Kind::Term => format!("sparql_num({})", self.sql),
+            Kind::Boolean => format!("CAST({} AS DOUBLE)", self.sql),
+            Kind::Numeric => self.sql,
+            Kind::String => format!("TRY_CAST({} AS DOUBLE)", self.sql),
+        }
+    }
+
+    /// Converts the value to a lexical form.
+    fn string(self) -> String {
+        match self.kind {
+            Kind::Term => format!("sparql_lex({})", self.sql),
+            Kind::Boolean | Kind::Numeric => format!("CAST({} AS VARCHAR)", self.sql),
+            Kind::String => self.sql,
+        }
+    }
+}
+
+/// A translated graph pattern: an SQL query, the variables bound by its columns, and the
+/// variables whose column may be NULL because some solutions leave them unbound.
+struct SqlPattern {
+    sql: String,
+    variables: Vec<String>,
+    maybe_unbound: HashSet<String>,
+}
+
+impl SqlPattern {
+    /// Returns `true` if every solution binds `variable`.
+    fn always_binds(&self, variable: &str) -> bool {
+        self.variables.iter().any(|v| v == variable) && !self.maybe_unbound.contains(variable)
+    }
+}
+
+/// Builds the condition under which the values of a variable shared by the `l` and `r` sides
+/// of a join are compatible.
+///
+/// Variables bound in every solution of both sides are compared with a plain equality, which
+/// DuckDB uses as a hash join key. The NULL-tolerant comparison, which forces a nested loop
+/// join, is only used for variables that one side may leave unbound.
+fn compatible(variable: &str, left: &SqlPattern, right: &SqlPattern) -> String {
+    let (l, r) = (
+        format!("l.{}", sql_identifier(variable)),
+        format!("r.{}", sql_identifier(variable)),
+    );
+    if left.always_binds(variable) && right.always_binds(variable) {
+        format!("{l} = {r}")
+    } else {
+        format!("({l} = {r} OR {l} IS NULL OR {r} IS NULL)")
+    }
+}
+
+/// Builds a `SELECT` list, using a placeholder column when there are no variables.
+fn select_list(columns: Vec<String>) -> String {
+    if columns.is_empty() {
+        "1 AS __unit".to_string()
+    } else {
+        columns.join(", ")
+    }
+}
+
+/// Translates SPARQL algebra into DuckDB SQL over a set of Parquet files.
+struct Translator {
+    parquet_files: String,
+    /// Whether any of the files has a graph column.
+    quad_mode: bool,
+}
+
+impl Translator {
+    fn new(cottas_file_paths: &[String], quad_mode: bool) -> Self {
+        let parquet_files = cottas_file_paths
+            .iter()
+            .map(|p| sql_string(p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self {
+            parquet_files,
+            quad_mode,
+        }
+    }
+
+    /// Translates a graph pattern, with `graph` as the active graph if inside `GRAPH`.
+    fn pattern(
+        &self,
+        pattern: &GraphPattern,
+        graph: Option<&NamedNodePattern>,
+    ) -> Result<SqlPattern, CottasError> {
+        match pattern {
+            GraphPattern::Bgp { patterns } => Ok(self.bgp(patterns, graph)),
+            GraphPattern::Join { left, right } => {
+                let left = self.pattern(left, graph)?;
+                let right = self.pattern(right, graph)?;
+                self.join(left, right, "JOIN", None)
+            }
+            GraphPattern::LeftJoin {
+                left,
+                right,
+                expression,
+            } => {
+                let left = self.pattern(left, graph)?;
+                let right = self.pattern(right, graph)?;
+                self.join(left, right, "LEFT JOIN", expression.as_ref())
+            }
+            GraphPattern::Filter { expr, inner } => {
+                let inner = self.pattern(inner, graph)?;
+                let condition = self
+                    .expression(expr, &|v| {
+                        inner
+                            .variables
+                            .iter()
+                            .any(|c| c == v)
+                            .then(|| sql_identifier(v))
+                    })?
+                    .boolean();
+                Ok(SqlPattern {
+                    sql: format!(
+                        "SELECT * FROM ({}) WHERE COALESCE({}, FALSE)",
+                        inner.sql, condition
+                    ),
+                    variables: inner.variables,
+                    maybe_unbound: inner.maybe_unbound,
+                })
+            }
+            GraphPattern::Union { left, right } => {
+                let left = self.pattern(left, graph)?;
+                let right = self.pattern(right, graph)?;
+                let mut variables = left.variables.clone();
+                for variable in &right.variables {
+                    if !variables.contains(variable) {
+                        variables.push(variable.clone());
+                    }
+                }
+                let columns = |side: &SqlPattern| {
+                    select_list(
+                        variables
+                            .iter()
+                            .map(|v| {
+                                if side.variables.contains(v) {
+                                    sql_identifier(v)
+                                } else {
+                                    format!("CAST(NULL AS VARCHAR) AS {}", sql_identifier(v))
+                                }
+                            })
+                            .collect(),
+                    )
+                };
+                let maybe_unbound = variables
+                    .iter()
+                    .filter(|v| !left.always_binds(v) || !right.always_binds(v))
+                    .cloned()
+                    .collect();
+                Ok(SqlPattern {
+                    sql: format!(
+                        "SELECT {} FROM ({}) UNION ALL SELECT {} FROM ({})",
+                        columns(&left),
+                        left.sql,
+                        columns(&right),
+                        right.sql
+                    ),
+                    variables,
+                    maybe_unbound,
+                })
+            }
+            GraphPattern::Graph { name, inner } => self.pattern(inner, Some(name)),
+            GraphPattern::Extend {
+                inner,
+                variable,
+                expression,
+            } => {
+                let inner = self.pattern(inner, graph)?;
+                let value = self
+                    .expression(expression, &|v| {
+                        inner
+                            .variables
+                            .iter()
+                            .any(|c| c == v)
+                            .then(|| sql_identifier(v))
+                    })?
+                    .term();
+                let mut columns: Vec<_> =
+                    inner.variables.iter().map(|v| sql_identifier(v)).collect();
+                columns.push(format!(
+                    "{} AS {}",
+                    value,
+                    sql_identifier(variable.as_str())
+                ));
+                let mut variables = inner.variables;
+                variables.push(variable.as_str().to_string());
+                // The expression is unbound where it raises an error
+                let mut maybe_unbound = inner.maybe_unbound;
+                maybe_unbound.insert(variable.as_str().to_string());
+                Ok(SqlPattern {
+                    sql: format!("SELECT {} FROM ({})", columns.join(", "), inner.sql),
+                    variables,
+                    maybe_unbound,
+                })
+            }
+            GraphPattern::Minus { left, right } => {
+                let left = self.pattern(left, graph)?;
+                let right = self.pattern(right, graph)?;
+                let shared: Vec<_> = left
+                    .variables
+                    .iter()
+                    .filter(|v| right.variables.contains(v))
+                    .collect();
+                if shared.is_empty() {
+                    return Ok(left);
+                }
+                let mut conditions: Vec<String> = shared
+                    .iter()
+                    .map(|v| compatible(v, &left, &right))
+                    .collect();
+                // The solutions must share a bound variable, which always holds if a shared
+                // variable is bound on both sides
+                if !shared
+                    .iter()
+                    .any(|v| left.always_binds(v) && right.always_binds(v))
+                {
+                    let overlapping = shared
+                        .iter()
+                        .map(|v| {
+                            format!(
+                                "(l.{c} IS NOT NULL AND r.{c} IS NOT NULL)",
+                                c = sql_identifier(v)
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" OR ");
+                    conditions.push(format!("({})", overlapping));
+                }
+                Ok(SqlPattern {
+                    sql: format!(
+                        "SELECT l.* FROM ({}) AS l WHERE NOT EXISTS (SELECT 1 FROM ({}) AS r WHERE {})",
+                        left.sql,
+                        right.sql,
+                        conditions.join(" AND ")
+                    ),
+                    variables: left.variables,
+                    maybe_unbound: left.maybe_unbound,
+                })
+            }
+            GraphPattern::Values {
+                variables,
+                bindings,
+            } => {
+                let variables: Vec<String> =
+                    variables.iter().map(|v| v.as_str().to_string()).collect();
+                if variables.is_empty() || bindings.is_empty() {
+                    return Err(unsupported("VALUES without variables or rows"));
+                }
+                let rows = bindings
+                    .iter()
+                    .map(|row| {
+                        let values = row
+                            .iter()
+                            .map(|term| match term {
+                                Some(term) => {
+                                    format!("CAST({} AS VARCHAR)", sql_string(&term.to_string()))
+                                }
+                                None => "CAST(NULL AS VARCHAR)".to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("({})", values)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let columns = variables
+                    .iter()
+                    .map(|v| sql_identifier(v))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let maybe_unbound = variables
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| bindings.iter().any(|row| row[*i].is_none()))
+                    .map(|(_, v)| v.clone())
+                    .collect();
+                Ok(SqlPattern {
+                    sql: format!("SELECT * FROM (VALUES {}) AS v({})", rows, columns),
+                    variables,
+                    maybe_unbound,
+                })
+            }
+            GraphPattern::Group {
+                inner,
+                variables,
+                aggregates,
+            } => {
+                let inner = self.pattern(inner, graph)?;
+                let resolve = |v: &str| {
+                    inner
+                        .variables
+                        .iter()
+                        .any(|c| c == v)
+                        .then(|| sql_identifier(v))
+                };
+                let group_variables: Vec<String> =
+                    variables.iter().map(|v| v.as_str().to_string()).collect();
+                let mut columns: Vec<String> = group_variables
+                    .iter()
+                    .map(|v| match resolve(v) {
+                        Some(column) => column,
+                        None => format!("CAST(NULL AS VARCHAR) AS {}", sql_identifier(v)),
+                    })
+                    .collect();
+                let mut result_variables = group_variables.clone();
+                let mut maybe_unbound: HashSet<String> = group_variables
+                    .iter()
+                    .filter(|v| !inner.always_binds(v))
+                    .cloned()
+                    .collect();
+                let row = match inner.variables.len() {
+                    0 => "1".to_string(),
+                    _ => format!(
+                        "ROW({})",
+                        inner
+                            .variables
+                            .iter()
+                            .map(|v| sql_identifier(v))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                for (variable, aggregate) in aggregates {
+                    let value = self.aggregate(aggregate, &row, &resolve)?.term();
+                    columns.push(format!(
+                        "{} AS {}",
+                        value,
+                        sql_identifier(variable.as_str())
+                    ));
+                    result_variables.push(variable.as_str().to_string());
+                    maybe_unbound.insert(variable.as_str().to_string());
+                }
+                let group_by = group_variables
+                    .iter()
+                    .filter_map(|v| resolve(v))
+                    .collect::<Vec<_>>();
+                let mut sql = format!("SELECT {} FROM ({})", select_list(columns), inner.sql);
+                if !group_by.is_empty() {
+                    sql.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+                }
+                Ok(SqlPattern {
+                    sql,
+                    variables: result_variables,
+                    maybe_unbound,
+                })
+            }
+            GraphPattern::Project { .. }
+            | GraphPattern::Distinct { .. }
+            | GraphPattern::Reduced { .. }
+            | GraphPattern::Slice { .. }
+            | GraphPattern::OrderBy { .. } => self.modifiers(pattern, graph),
+            GraphPattern::Path { .. } => Err(unsupported("property paths")),
+            GraphPattern::Service { .. } => Err(unsupported("SERVICE")),
+        }
+    }
+
+    /// Translates the solution modifiers on top of a pattern into a single SQL `SELECT`, so
+    /// that `ORDER BY` is applied together with `DISTINCT`, `LIMIT`, and `OFFSET`.
+    fn modifiers(
+        &self,
+        pattern: &GraphPattern,
+        graph: Option<&NamedNodePattern>,
+    ) -> Result<SqlPattern, CottasError> {
+        let mut pattern = pattern;
+        let mut slice = None;
+        let mut distinct = false;
+        let mut projection = None;
+        let mut order = None;
+
+        if let GraphPattern::Slice {
+            inner,
+            start,
+            length,
+        } = pattern
+        {
+            slice = Some((*start, *length));
+            pattern = inner;
+        }
+        if let GraphPattern::Distinct { inner } | GraphPattern::Reduced { inner } = pattern {
+            distinct = matches!(pattern, GraphPattern::Distinct { .. });
+            pattern = inner;
+        }
+        if let GraphPattern::Project { inner, variables } = pattern {
+            projection = Some(variables);
+            pattern = inner;
+        }
+        if let GraphPattern::OrderBy { inner, expression } = pattern {
+            order = Some(expression);
+            pattern = inner;
+        }
+
+        let inner = self.pattern(pattern, graph)?;
+        let resolve = |v: &str| {
+            inner
+                .variables
+                .iter()
+                .any(|c| c == v)
+                .then(|| sql_identifier(v))
+        };
+
+        let variables: Vec<String> = match projection {
+            Some(variables) => variables.iter().map(|v| v.as_str().to_string()).collect(),
+            None => inner.variables.clone(),
+        };
+        let columns = variables
+            .iter()
+            .map(|v| match resolve(v) {
+                Some(column) => column,
+                None => format!("CAST(NULL AS VARCHAR) AS {}", sql_identifier(v)),
+            })
+            .collect();
+
+        // Unbound values sort first, then blank nodes, IRIs, and literals, and DESC is the
+        // exact reverse of ASC
+        let mut keys = Vec::new();
+        for condition in order.into_iter().flatten() {
+            let (expression, direction) = match condition {
+                OrderExpression::Asc(e) => (e, "ASC NULLS FIRST"),
+                OrderExpression::Desc(e) => (e, "DESC NULLS LAST"),
+            };
+            let value = self.expression(expression, &resolve)?.term();
+            keys.push(format!(
+                "sparql_kind({v}) {d}, sparql_num({v}) {d}, {v} {d}",
+                v = value,
+                d = direction
+            ));
+        }
+
+        let mut sql = if distinct && !keys.is_empty() {
+            // The keys may use variables that are not projected, so the solutions are ranked in
+            // a subquery and deduplicated there, keeping the rank of their first occurrence
+            let names = if variables.is_empty() {
+                "__unit".to_string()
+            } else {
+                variables
+                    .iter()
+                    .map(|v| sql_identifier(v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            format!(
+                "SELECT {names} FROM (SELECT {}, row_number() OVER (ORDER BY {}) AS __rank \
+                 FROM ({})) GROUP BY {names} ORDER BY MIN(__rank)",
+                select_list(columns),
+                keys.join(", "),
+                inner.sql
+            )
+        } else {
+            let mut sql = format!(
+                "SELECT {}{} FROM ({})",
+                if distinct { "DISTINCT " } else { "" },
+                select_list(columns),
+                inner.sql
+            );
+            if !keys.is_empty() {
+                sql.push_str(&format!(" ORDER BY {}", keys.join(", ")));
+            }
+            sql
+        };
+
+        if let Some((start, length)) = slice {
+            if let Some(length) = length {
+                sql.push_str(&format!(" LIMIT {}", length));
+            }
+            if start > 0 {
+                sql.push_str(&format!(" OFFSET {}", start));
+            }
+        }
+
+        let maybe_unbound = variables
+            .iter()
+            .filter(|v| !inner.always_binds(v))
+            .cloned()
+            .collect();
+
+        Ok(SqlPattern {
+            sql,
+            variables,
+            maybe_unbound,
+        })
+    }
+
+    /// Translates a basic graph pattern into a join of one Parquet scan per triple pattern.
+    fn bgp(&self, patterns: &[TriplePattern], graph: Option<&NamedNodePattern>) -> SqlPattern {
+        if patterns.is_empty() {
+            return SqlPattern {
+                sql: "SELECT 1 AS __unit".to_string(),
+                variables: Vec::new(),
+                maybe_unbound: HashSet::new(),
+            };
+        }
+
+        let mut variables: Vec<String> = Vec::new();
+        let mut columns: HashMap<String, String> = HashMap::new();
+        let mut from = Vec::with_capacity(patterns.len());
+        let mut conditions = Vec::new();
+
+        let mut bind = |name: String, column: String, conditions: &mut Vec<String>| {
+            if let Some(first) = columns.get(&name) {
+                conditions.push(format!("{} = {}", column, first));
+            } else {
+                if !name.starts_with("__bnode_") {
+                    variables.push(name.clone());
+                }
+                columns.insert(name, column);
+            }
+        };
+
+        for (t, pattern) in patterns.iter().enumerate() {
+            let scan = format!(
+                "PARQUET_SCAN([{}], union_by_name = true)",
+                self.parquet_files
+            );
+            if graph.is_some() && !self.quad_mode {
+                // Files of triples have no named graphs, so `GRAPH` matches nothing in them
+                from.push(format!(
+                    "(SELECT *, CAST(NULL AS VARCHAR) AS g FROM {}) AS t{}",
+                    scan, t
+                ));
+            } else {
+                from.push(format!("{} AS t{}", scan, t));
+            }
+
+            let mut positions: Vec<(String, Option<String>)> = Vec::with_capacity(4);
+            positions.push(("s".to_string(), term_pattern_name(&pattern.subject)));
+            positions.push(("p".to_string(), named_node_pattern_name(&pattern.predicate)));
+            positions.push(("o".to_string(), term_pattern_name(&pattern.object)));
+            if let Some(graph) = graph {
+                positions.push(("g".to_string(), named_node_pattern_name(graph)));
+                conditions.push(format!("t{}.g IS NOT NULL", t));
+            }
+
+            let constants = [
+                pattern.subject.to_string(),
+                pattern.predicate.to_string(),
+                pattern.object.to_string(),
+                graph.map(|g| g.to_string()).unwrap_or_default(),
+            ];
+
+            for (i, (column, variable)) in positions.into_iter().enumerate() {
+                let column = format!("t{}.{}", t, column);
+                match variable {
+                    Some(name) => bind(name, column, &mut conditions),
+                    None => conditions.push(format!("{} = {}", column, sql_string(&constants[i]))),
+                }
+            }
+        }
+
+        let select = variables
+            .iter()
+            .map(|v| format!("{} AS {}", columns[v], sql_identifier(v)))
+            .collect();
+        let mut sql = format!("SELECT {} FROM {}", select_list(select), from.join(", "));
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+
+        SqlPattern {
+            sql,
+            variables,
+            maybe_unbound: HashSet::new(),
+        }
+    }
+
+    /// Joins two patterns on their shared variables, following SPARQL compatibility rules.
+    fn join(
+        &self,
+        left: SqlPattern,
+        right: SqlPattern,
+        join: &str,
+        expression: Option<&Expression>,
+    ) -> Result<SqlPattern, CottasError> {
+        let mut variables = left.variables.clone();
+        let mut columns = Vec::new();
+        let mut conditions = Vec::new();
+        let mut resolved = HashMap::new();
+
+        for v in &left.variables {
+            let l = format!("l.{}", sql_identifier(v));
+            if right.variables.contains(v) {
+                conditions.push(compatible(v, &left, &right));
+                if left.always_binds(v) {
+                    resolved.insert(v.clone(), l);
+                } else {
+                    resolved.insert(v.clone(), format!("COALESCE({l}, r.{})", sql_identifier(v)));
+                }
+            } else {
+                resolved.insert(v.clone(), l);
+            }
+        }
+        for v in &right.variables {
+            if !left.variables.contains(v) {
+                resolved.insert(v.clone(), format!("r.{}", sql_identifier(v)));
+                variables.push(v.clone());
+            }
+        }
+        for v in &variables {
+            columns.push(format!("{} AS {}", resolved[v], sql_identifier(v)));
+        }
+
+        if let Some(expression) = expression {
+            let condition = self
+                .expression(expression, &|v| resolved.get(v).cloned())?
+                .boolean();
+            conditions.push(format!("COALESCE({}, FALSE)", condition));
+        }
+
+        let on = if conditions.is_empty() {
+            "TRUE".to_string()
+        } else {
+            conditions.join(" AND ")
+        };
+
+        // The right side of a LEFT JOIN leaves its variables unbound when nothing matches
+        let maybe_unbound = variables
+            .iter()
+            .filter(|v| !left.always_binds(v) && (join != "JOIN" || !right.always_binds(v)))
+            .cloned()
+            .collect();
+
+        Ok(SqlPattern {
+            sql: format!(
+                "SELECT {} FROM ({}) AS l {} ({}) AS r ON {}",
+                select_list(columns),
+                left.sql,
+                join,
+                right.sql,
+                on
+            ),
+            variables,
+            maybe_unbound,
+        })
+    }
+
+    /// Translates an aggregate over the columns resolved by `resolve`, where `row` is an SQL
+    /// value identifying a whole solution.
+    fn aggregate(
+        &self,
+        aggregate: &AggregateExpression,
+        row: &str,
+        resolve: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<SqlExpression, CottasError> {
+        let (name, expr, distinct) = match aggregate {
+            AggregateExpression::CountSolutions { distinct } => {
+                let sql = if *distinct {
+                    format!("CAST(COUNT(DISTINCT {}) AS DOUBLE)", row)
+                } else {
+                    "CAST(COUNT(*) AS DOUBLE)".to_string()
+                };
+                return Ok(SqlExpression::new(sql, Kind::Numeric));
+            }
+            AggregateExpression::FunctionCall {
+                name,
+                expr,
+                distinct,
+            } => (name, expr, *distinct),
+        };
+
+        let distinct = if distinct { "DISTINCT " } else { "" };
+        let value = self.expression(expr, resolve)?;
+
+        Ok(match name {
+            AggregateFunction::Count => SqlExpression::new(
+                format!("CAST(COUNT({}{}) AS DOUBLE)", distinct, value.term()),
+                Kind::Numeric,
+            ),
+            AggregateFunction::Sum => SqlExpression::new(
+                format!("COALESCE(SUM({}{}), 0)", distinct, value.numeric()),
+                Kind::Numeric,
+            ),
+            AggregateFunction::Avg => SqlExpression::new(
+                format!("COALESCE(AVG({}{}), 0)", distinct, value.numeric()),
+                Kind::Numeric,
+            ),
+            AggregateFunction::Min | AggregateFunction::Max => {
+                let (by_number, by_string) = if matches!(name, AggregateFunction::Min) {
+                    ("ARG_MIN", "MIN")
+                } else {
+                    ("ARG_MAX", "MAX")
+                };
+                let term = value.term();
+                SqlExpression::new(
+                    format!(
+                        "CASE WHEN COUNT(sparql_num({t})) = COUNT({t}) THEN {}({t}, sparql_num({t})) ELSE {}({t}) END",
+                        by_number,
+                        by_string,
+                        t = term
+                    ),
+                    Kind::Term,
+                )
+            }
+            AggregateFunction::Sample => {
+                SqlExpression::new(format!("ANY_VALUE({})", value.term()), Kind::Term)
+            }
+            AggregateFunction::GroupConcat { separator } => SqlExpression::new(
+                format!(
+                    "COALESCE(STRING_AGG({}{}, {}), '')",
+                    distinct,
+                    value.string(),
+                    sql_string(separator.as_deref().unwrap_or(" "))
+                ),
+                Kind::String,
+            ),
+            AggregateFunction::Custom(name) => {
+                return Err(unsupported(&format!("aggregate {}", name)))
+            }
+        })
+    }
+
+    /// Translates an expression over the columns resolved by `resolve`.
+    fn expression(
+        &self,
+        expression: &Expression,
+        resolve: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<SqlExpression, CottasError> {
+        let expr = |e: &Expression| self.expression(e, resolve);
+        let binary =
+            |a: &Expression, b: &Expression, op: &str| -> Result<SqlExpression, CottasError> {
+                Ok(SqlExpression::new(
+                    format!("({} {} {})", expr(a)?.numeric(), op, expr(b)?.numeric()),
+                    Kind::Numeric,
+                ))
+            };
+        let logical =
+            |a: &Expression, b: &Expression, op: &str| -> Result<SqlExpression, CottasError> {
+                Ok(SqlExpression::new(
+                    format!("({} {} {})", expr(a)?.boolean(), op, expr(b)?.boolean()),
+                    Kind::Boolean,
+                ))
+            };
+
+        Ok(match expression {
+            Expression::NamedNode(node) => {
+                SqlExpression::new(sql_string(&node.to_string()), Kind::Term)
+            }
+            Expression::Literal(literal) => {
+                SqlExpression::new(sql_string(&literal.to_string()), Kind::Term)
+            }
+            Expression::Variable(variable) => SqlExpression::new(
+                resolve(variable.as_str()).unwrap_or_else(|| "CAST(NULL AS VARCHAR)".to_string()),
+                Kind::Term,
+            ),
+            Expression::Or(a, b) => logical(a, b, "OR")?,
+            Expression::And(a, b) => logical(a, b, "AND")?,
+            Expression::Not(a) => {
+                SqlExpression::new(format!("(NOT {})", expr(a)?.boolean()), Kind::Boolean)
+            }
+            Expression::Equal(a, b) => self.compare(expr(a)?, expr(b)?, "=")?,
+            Expression::Greater(a, b) => self.compare(expr(a)?, expr(b)?, ">")?,
+            Expression::GreaterOrEqual(a, b) => self.compare(expr(a)?, expr(b)?, ">=")?,
+            Expression::Less(a, b) => self.compare(expr(a)?, expr(b)?, "<")?,
+            Expression::LessOrEqual(a, b) => self.compare(expr(a)?, expr(b)?, "<=")?,
+            Expression::SameTerm(a, b) => SqlExpression::new(
+                format!("({} = {})", expr(a)?.term(), expr(b)?.term()),
+                Kind::Boolean,
+            ),
+            Expression::In(a, list) => {
+                let mut alternatives = Vec::with_capacity(list.len());
+                for b in list {
+                    alternatives.push(self.compare(expr(a)?, expr(b)?, "=")?.sql);
+                }
+                if alternatives.is_empty() {
+                    SqlExpression::new("FALSE".to_string(), Kind::Boolean)
+                } else {
+                    SqlExpression::new(format!("({})", alternatives.join(" OR ")), Kind::Boolean)
+                }
+            }
+            Expression::Add(a, b) => binary(a, b, "+")?,
+            Expression::Subtract(a, b) => binary(a, b, "-")?,
+            Expression::Multiply(a, b) => binary(a, b, "*")?,
+            Expression::Divide(a, b) => SqlExpression::new(
+                format!(
+                    "({} / NULLIF({}, 0))",
+                    expr(a)?.numeric(),
+                    expr(b)?.numeric()
+                ),
+                Kind::Numeric,
+            ),
+            Expression::UnaryPlus(a) => SqlExpression::new(expr(a)?.numeric(), Kind::Numeric),
+            Expression::UnaryMinus(a) => {
+                SqlExpression::new(format!("(-{})", expr(a)?.numeric()), Kind::Numeric)
+            }
+            Expression::Bound(variable) => SqlExpression::new(
+                match resolve(variable.as_str()) {
+                    Some(column) => format!("({} IS NOT NULL)", column),
+                    None => "FALSE".to_string(),
+                },
+                Kind::Boolean,
+            ),
+            Expression::If(condition, then, otherwise) => SqlExpression::new(
+                format!(
+                    "(CASE WHEN {} THEN {} ELSE {} END)",
+                    expr(condition)?.boolean(),
+                    expr(then)?.term(),
+                    expr(otherwise)?.term()
+                ),
+                Kind::Term,
+            ),
+            Expression::Coalesce(list) => {
+                let terms = list
+                    .iter()
+                    .map(|e| Ok(expr(e)?.term()))
+                    .collect::<Result<Vec<_>, CottasError>>()?;
+                SqlExpression::new(format!("COALESCE({})", terms.join(", ")), Kind::Term)
+            }
+            Expression::FunctionCall(function, args) => {
+                let args = args.iter().map(expr).collect::<Result<Vec<_>, _>>()?;
+                self.function(function, args)?
+            }
+            Expression::Exists(_) => return Err(unsupported("EXISTS")),
+        })
+    }
+
+    /// Compares two values, numerically if both are numbers and lexically otherwise.
+    fn compare(
+        &self,
+        a: SqlExpression,
+        b: SqlExpression,
+        op: &str,
+    ) -> Result<SqlExpression, CottasError> {
+        let sql = if a.kind == Kind::Numeric || b.kind == Kind::Numeric {
+            format!("({} {} {})", a.numeric(), op, b.numeric())
+        } else if a.kind == Kind::Boolean || b.kind == Kind::Boolean {
+            format!("({} {} {})", a.boolean(), op, b.boolean())
+        } else if a.kind == Kind::String || b.kind == Kind::String {
+            format!("({} {} {})", a.string(), op, b.string())
+        } else if op == "=" {
+            format!("sparql_eq({}, {})", a.sql, b.sql)
+        } else {
+            format!("(sparql_cmp({}, {}) {} 0)", a.sql, b.sql, op)
+        };
+        Ok(SqlExpression::new(sql, Kind::Boolean))
+    }
+
+    /// Translates a call to a built-in SPARQL function.
+    fn function(
+        &self,
+        function: &Function,
+        mut args: Vec<SqlExpression>,
+    ) -> Result<SqlExpression, CottasError> {
+        let arity = match function {
+            Function::Str
+            | Function::Lang
+            | Function::Datatype
+            | Function::IsIri
+            | Function::IsBlank
+            | Function::IsLiteral
+            | Function::IsNumeric
+            | Function::UCase
+            | Function::LCase
+            | Function::StrLen => 1,
+            Function::LangMatches
+            | Function::Contains
+            | Function::StrStarts
+            | Function::StrEnds => 2,
+            Function::Regex => args.len().clamp(2, 3),
+            _ => return Err(unsupported(&format!("function {}", function))),
+        };
+        if args.len() != arity {
+            return Err(CottasError::InvalidQuery(format!(
+                "wrong number of arguments for {}",
+                function
+            )));
+        }
+
+        let first = args.remove(0);
+        Ok(match function {
+            Function::Str => SqlExpression::new(first.string(), Kind::String),
+            Function::Lang => {
+                SqlExpression::new(format!("sparql_lang({})", first.term()), Kind::String)
+            }
+            Function::Datatype => SqlExpression::new(
+                format!("('<' || sparql_datatype({}) || '>')", first.term()),
+                Kind::Term,
+            ),
+            Function::IsIri => {
+                SqlExpression::new(format!("starts_with({}, '<')", first.term()), Kind::Boolean)
+            }
+            Function::IsBlank => SqlExpression::new(
+                format!("starts_with({}, '_:')", first.term()),
+                Kind::Boolean,
+            ),
+            Function::IsLiteral => SqlExpression::new(
+                format!("starts_with({}, '\"')", first.term()),
+                Kind::Boolean,
+            ),
+            Function::IsNumeric => SqlExpression::new(
+                format!("(sparql_num({}) IS NOT NULL)", first.term()),
+                Kind::Boolean,
+            ),
+            Function::UCase => {
+                SqlExpression::new(format!("upper({})", first.string()), Kind::String)
+            }
+            Function::LCase => {
+                SqlExpression::new(format!("lower({})", first.string()), Kind::String)
+            }
+            Function::StrLen => SqlExpression::new(
+                format!("CAST(length({}) AS DOUBLE)", first.string()),
+                Kind::Numeric,
+            ),
+            Function::LangMatches => {
+                let range = args.remove(0).string();
+                let tag = first.string();
+                SqlExpression::new(
+                    format!(
+                        "(CASE WHEN {r} = '*' THEN {t} <> '' ELSE lower({t}) = lower({r}) OR starts_with(lower({t}), lower({r}) || '-') END)",
+                        r = range,
+                        t = tag
+                    ),
+                    Kind::Boolean,
+                )
+            }
+            Function::Contains => SqlExpression::new(
+                format!("contains({}, {})", first.string(), args.remove(0).string()),
+                Kind::Boolean,
+            ),
+            Function::StrStarts => SqlExpression::new(
+                format!(
+                    "starts_with({}, {})",
+                    first.string(),
+                    args.remove(0).string()
+                ),
+                Kind::Boolean,
+            ),
+            Function::StrEnds => SqlExpression::new(
+                format!("ends_with({}, {})", first.string(), args.remove(0).string()),
+                Kind::Boolean,
+            ),
+            Function::Regex => {
+                let pattern = args.remove(0).string();
+                let flags = match args.pop() {
+                    Some(flags) => flags.string(),
+                    None => "''".to_string(),
+                };
+                SqlExpression::new(
+                    format!("regexp_matches({}, {}, {})", first.string(), pattern, flags),
+                    Kind::Boolean,
+                )
+            }
+            _ => return Err(unsupported(&format!("function {}", function))),
+        })
+    }
+}
+
+/// Returns the column name bound by a subject or object pattern, or `None` for a constant.
+///
+/// Blank nodes act as variables that are not projected.
+fn term_pattern_name(pattern: &TermPattern) -> Option<String> {
+    match pattern {
+        TermPattern::Variable(variable) => Some(variable.as_str().to_string()),
+        TermPattern::BlankNode(node) => Some(format!("__bnode_{}", node.as_str())),
+        _ => None,
+    }
+}
+
+/// Returns the column name bound by a predicate or graph pattern, or `None` for a constant.
+fn named_node_pattern_name(pattern: &NamedNodePattern) -> Option<String> {
+    match pattern {
+        NamedNodePattern::Variable(variable) => Some(variable.as_str().to_string()),
+        NamedNodePattern::NamedNode(_) => None,
+    }
+}
+
+/// Runs a translated pattern and returns the terms bound to `variables` in every solution.
+fn run_pattern(
+    conn: &Connection,
+    pattern: &SqlPattern,
+) -> Result<Vec<Vec<Option<Term>>>, CottasError> {
+    let columns = pattern
+        .variables
+        .iter()
+        .map(|v| sql_identifier(v))
+        .collect::<Vec<_>>();
+    let sql = format!("SELECT {} FROM ({})", select_list(columns), pattern.sql);
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+    let mut solutions = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let mut solution = Vec::with_capacity(pattern.variables.len());
+        for i in 0..pattern.variables.len() {
+            let value: Option<String> = row.get(i)?;
+            solution.push(value.as_deref().map(parse_term).transpose()?);
+        }
+        solutions.push(solution);
+    }
+
+    Ok(solutions)
+}
+
+/// Instantiates a CONSTRUCT template with the solutions of its pattern.
+///
+/// Triples that are not valid RDF (e.g., with a literal subject or an unbound variable) are
+/// skipped, and template blank nodes are renamed for every solution.
+fn construct(
+    template: &[TriplePattern],
+    variables: &[String],
+    solutions: &[Vec<Option<Term>>],
+) -> Vec<Triple> {
+    let mut triples = Vec::new();
+
+    for solution in solutions {
+        let mut blank_nodes: HashMap<String, BlankNode> = HashMap::new();
+        let mut instantiate = |pattern: &TermPattern| -> Option<String> {
+            match pattern {
+                TermPattern::Variable(variable) => {
+                    let i = variables.iter().position(|v| v == variable.as_str())?;
+                    solution[i].as_ref().map(|term| term.to_string())
+                }
+                TermPattern::BlankNode(node) => Some(
+                    blank_nodes
+                        .entry(node.as_str().to_string())
+                        .or_default()
+                        .to_string(),
+                ),
+                other => Some(other.to_string()),
+            }
+        };
+
+        for pattern in template {
+            let subject = instantiate(&pattern.subject);
+            let predicate = match &pattern.predicate {
+                NamedNodePattern::NamedNode(node) => Some(node.to_string()),
+                NamedNodePattern::Variable(variable) => {
+                    instantiate(&TermPattern::Variable(variable.clone()))
+                }
+            };
+            let object = instantiate(&pattern.object);
+
+            if let (Some(s), Some(p), Some(o)) = (subject, predicate, object) {
+                if let Ok(quad) = parse_quad(&s, &p, &o, None) {
+                    triples.push(Triple::from(quad));
+                }
+            }
+        }
+    }
+
+    triples
+}
+
+/// Evaluates a SPARQL query over one or more Parquet files using DuckDB.
+///
+/// SELECT, ASK, and CONSTRUCT queries are supported, with basic graph patterns, `GRAPH`,
+/// `FILTER`, `OPTIONAL`, `UNION`, `MINUS`, `VALUES`, `BIND`, aggregates, and solution modifiers.
+///
+/// # Arguments
+///
+/// * `cottas_file_paths` - Paths to the Parquet files queried as a single dataset.
+/// * `query` - The SPARQL query.
+///
+/// # Returns
+///
+/// * `Ok(QueryResults)` - The solutions, boolean, or triples produced by the query.
+///
+/// # Errors
+///
+/// Returns an error if the query is invalid, uses an unsupported feature, or fails.
+pub fn query_in_duckdb(
+    cottas_file_paths: &[String],
+    query: &str,
+) -> Result<QueryResults, CottasError> {
+    let conn = connection_in_memory()?;
+    let query = parse_query(&conn, cottas_file_paths, query)?;
+    evaluate_query(&conn, cottas_file_paths, query)
+}
+
+/// Parses a SPARQL query with the prefixes and base IRI stored in the queried files.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_paths` - Paths to the Parquet files queried as a single dataset.
+/// * `query` - The SPARQL query.
+///
+/// # Errors
+///
+/// Returns an error if the metadata of the files cannot be read or the query is invalid.
+pub(crate) fn parse_query(
+    conn: &Connection,
+    cottas_file_paths: &[String],
+    query: &str,
+) -> Result<Query, CottasError> {
+    // Queries may use the prefixes and base IRI stored in the files
+    let namespaces = read_namespaces(conn, cottas_file_paths)?;
+    let mut parser = SparqlParser::new();
+    if let Some(base_iri) = &namespaces.base_iri {
+        parser = parser.with_base_iri(base_iri.as_str())?;
+    }
+    for (name, iri) in &namespaces.prefixes {
+        parser = parser.with_prefix(name.as_str(), iri.as_str())?;
+    }
+    Ok(parser.parse_query(query)?)
+}
+
+/// Evaluates a parsed SPARQL query over one or more Parquet files.
+///
+/// # Arguments
+///
+/// * `conn` - The DuckDB connection.
+/// * `cottas_file_paths` - Paths to the Parquet files queried as a single dataset.
+/// * `query` - The query returned by [`parse_query`].
+///
+/// # Errors
+///
+/// Returns an error if the query uses an unsupported feature or fails.
+pub(crate) fn evaluate_query(
+    conn: &Connection,
+    cottas_file_paths: &[String],
+    query: Query,
+) -> Result<QueryResults, CottasError> {
+    create_sparql_macros(conn)?;
+    let mut quad_mode = false;
+    for cottas_file_path in cottas_file_paths {
+        quad_mode |= has_column(conn, cottas_file_path, "g")?;
+    }
+    let translator = Translator::new(cottas_file_paths, quad_mode);
+
+    match query {
+        Query::Select {
+            dataset, pattern, ..
+        } => {
+            if dataset.is_some() {
+                return Err(unsupported("FROM clauses"));
+            }
+            let pattern = translator.pattern(&pattern, None)?;
+            let solutions = run_pattern(conn, &pattern)?;
+            let variables = pattern
+                .variables
+                .iter()
+                .map(|v| Variable::new(v.as_str()))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(QueryResults::Solutions {
+                variables,
+                solutions,
+            })
+        }
+        Query::Ask {
+            dataset, pattern, ..
+        } => {
+            if dataset.is_some() {
+                return Err(unsupported("FROM clauses"));
+            }
+            let pattern = translator.pattern(&pattern, None)?;
+            let exists: bool =
+                conn.query_row(&format!("SELECT EXISTS ({})", pattern.sql), [], |row| {
+                    row.get(0)
+                })?;
+            Ok(QueryResults::Boolean(exists))
+        }
+        Query::Construct {
+            template,
+            dataset,
+            pattern,
+            ..
+        } => {
+            if dataset.is_some() {
+                return Err(unsupported("FROM clauses"));
+            }
+            let pattern = translator.pattern(&pattern, None)?;
+            let solutions = run_pattern(conn, &pattern)?;
+            Ok(QueryResults::Graph(construct(
+                &template,
+                &pattern.variables,
+                &solutions,
+            )))
+        }
+        Query::Describe { .. } => Err(unsupported("DESCRIBE")),
+    }
+}
+
+/// Writes query results to a writer.
+///
+/// Solutions and booleans are serialized in the given SPARQL results format, and the triples
+/// of CONSTRUCT queries as N-Triples.
+///
+/// # Arguments
+///
+/// * `results` - The query results.
+/// * `format` - The SPARQL results format (JSON, XML, CSV, or TSV).
+/// * `writer` - The output sink.
+///
+/// # Errors
+///
+/// Returns an error if serialization or writing fails.
+pub fn write_query_results<W: Write>(
+    results: &QueryResults,
+    format: QueryResultsFormat,
+    writer: W,
+) -> Result<(), CottasError> {
+    write_query_results_with_rdf_format(results, format, RdfFormat::NTriples, writer)
+}
+
+/// Writes query results to a writer, serializing the triples of CONSTRUCT queries in the given
+/// RDF format.
+///
+/// # Arguments
+///
+/// * `results` - The query results.
+/// * `format` - The SPARQL results format of solutions and booleans.
+/// * `rdf_format` - The RDF format of the triples of CONSTRUCT queries.
+/// * `writer` - The output sink.
+///
+/// # Errors
+///
+/// Returns an error if serialization or writing fails.
+pub fn write_query_results_with_rdf_format<W: Write>(
+    results: &QueryResults,
+    format: QueryResultsFormat,
+    rdf_format: RdfFormat,
+    writer: W,
+) -> Result<(), CottasError> {
+    match results {
+        QueryResults::Solutions {
+            variables,
+            solutions,
+        } => {
+            let mut serializer = QueryResultsSerializer::from_format(format)
+                .serialize_solutions_to_writer(writer, variables.clone())?;
+            for solution in solutions {
+                serializer.serialize(
+                    variables
+                        .iter()
+                        .zip(solution)
+                        .filter_map(|(v, t)| t.as_ref().map(|t| (v.as_ref(), t.as_ref()))),
+                )?;
+            }
+            serializer.finish()?;
+        }
+        QueryResults::Boolean(value) => {
+            QueryResultsSerializer::from_format(format)
+                .serialize_boolean_to_writer(writer, *value)?;
+        }
+        QueryResults::Graph(triples) => {
+            let mut serializer = RdfSerializer::from_format(rdf_format).for_writer(writer);
+            for triple in triples {
+                serializer.serialize_triple(triple)?;
+            }
+            serializer.finish()?;
+        }
+    }
+    Ok(())
+}
@@ -1,7 +1,69 @@
-//! Utility functions for file format extraction, index validation, and SQL order building.
+//! Utility functions for file format extraction, index validation, and SQL clause building.
+
+use crate::error::CottasError;
+use crate::parser::Namespaces;
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+
+/// Version of the COTTAS file layout written by this library.
+pub const COTTAS_FORMAT_VERSION: &str = "1.0";
+
+/// Compression codecs for RDF files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Compression {
+    /// Gzip, for files ending in `.gz`.
+    Gzip,
+    /// Zstandard, for files ending in `.zst`.
+    Zstd,
+    /// Bzip2, for files ending in `.bz2`.
+    Bzip2,
+    /// XZ, for files ending in `.xz`.
+    Xz,
+}
+
+impl Compression {
+    /// Detects the compression of a file from its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file path as a string slice.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Compression)` - If the path ends in `.gz`, `.zst`, `.zstd`, `.bz2`, or `.xz`.
+    /// * `None` - If the file is not compressed.
+    pub fn from_path(path: &str) -> Option<Self> {
+        if path.ends_with(".gz") {
+            Some(Self::Gzip)
+        } else if path.ends_with(".zst") || path.ends_with(".zstd") {
+            Some(Self::Zstd)
+        } else if path.ends_with(".bz2") {
+            Some(Self::Bzip2)
+        } else if path.ends_with(".xz") {
+            Some(Self::Xz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Removes the compression extension (e.g., `.gz`) from a file path, if any.
+///
+/// # Arguments
+///
+/// * `path` - The file path as a string slice.
+pub fn strip_compression_extension(path: &str) -> &str {
+    [".gz", ".zst", ".zstd", ".bz2", ".xz"]
+        .iter()
+        .find_map(|extension| path.strip_suffix(extension))
+        .unwrap_or(path)
+}
 
 /// Extracts the RDF format from a file path based on its extension.
 ///
+/// Compression extensions are ignored, so `data.ttl.gz` is detected as Turtle.
+///
 /// # Arguments
 ///
 /// * `path` - The file path as a string slice.
@@ -11,6 +73,7 @@
 /// * `Some(&'static str)` - The RDF format as a string if recognized.
 /// * `None` - If the extension is not recognized.
 pub fn extract_format(path: &str) -> Option<&'static str> {
+    let path = strip_compression_extension(path);
     if path.ends_with(".ttl") {
         Some("turtle")
     } else if path.ends_with(".nt") {
@@ -21,6 +84,8 @@ pub fn extract_format(path: &str) -> Option<&'static str> {
         Some("trig")
     } else if path.ends_with(".rdf") || path.ends_with(".xml") {
         Some("xml")
+    } else if path.ends_with(".jsonld") {
+        Some("jsonld")
     } else {
         None
     }
@@ -46,8 +111,240 @@ pub fn is_valid_index(index: &str) -> bool {
     }
 }
 
+/// Checks that the index string is valid and applicable to the data being written.
+///
+/// A 4-letter index (e.g., "gspo") is only accepted for quad data, while a 3-letter index
+/// is accepted for both triples and quads.
+///
+/// # Arguments
+///
+/// * `index` - The index string (e.g., "spo", "gspo").
+/// * `quad_mode` - If true, the data holds quads.
+///
+/// # Errors
+///
+/// Returns an error if the index is invalid or requires a graph column that the data lacks.
+pub fn check_index(index: &str, quad_mode: bool) -> Result<(), CottasError> {
+    if !is_valid_index(index) {
+        return Err(CottasError::invalid_index(
+            index,
+            "expected a permutation of `spo` or `gspo`",
+        ));
+    }
+    if index.len() == 4 && !quad_mode {
+        return Err(CottasError::invalid_index(
+            index,
+            "it requires quad data, but the data has no graphs",
+        ));
+    }
+    Ok(())
+}
+
+/// Compression codecs for the columns of Cottas (Parquet) files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ParquetCodec {
+    /// No compression.
+    Uncompressed,
+    /// Snappy, fast and supported by every Parquet reader.
+    Snappy,
+    /// Gzip.
+    Gzip,
+    /// Zstandard, the default.
+    #[default]
+    Zstd,
+    /// Brotli.
+    Brotli,
+    /// LZ4.
+    Lz4,
+}
+
+impl ParquetCodec {
+    /// Returns the name of the codec in DuckDB `COPY` options.
+    fn sql_name(self) -> &'static str {
+        match self {
+            Self::Uncompressed => "UNCOMPRESSED",
+            Self::Snappy => "SNAPPY",
+            Self::Gzip => "GZIP",
+            Self::Zstd => "ZSTD",
+            Self::Brotli => "BROTLI",
+            Self::Lz4 => "LZ4",
+        }
+    }
+}
+
+/// Versions of the Parquet format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ParquetVersion {
+    /// Parquet 1.0, readable by older readers.
+    V1,
+    /// Parquet 2.0, the default.
+    #[default]
+    V2,
+}
+
+/// Options of the Parquet writer, shared by every function that writes a Cottas file.
+///
+/// The default writes Zstandard level 22 and Parquet 2.0 with dictionary encoding, which gives
+/// the smallest files but is slow for large datasets. DuckDB skips the row groups of
+/// [`DEFAULT_ROW_GROUP_SIZE`] rows whose statistics or bloom filters exclude a constant of a
+/// search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    /// Compression codec of the columns.
+    pub codec: ParquetCodec,
+    /// Compression level, only supported by Zstandard. `None` uses level 22 for Zstandard.
+    pub compression_level: Option<i32>,
+    /// Maximum number of rows per row group. `None` uses the DuckDB default.
+    ///
+    /// Smaller row groups make searches skip more rows, at the cost of a larger footer.
+    pub row_group_size: Option<u64>,
+    /// Maximum size in bytes of the dictionary page of a string column. `None` uses the
+    /// DuckDB default.
+    ///
+    /// DuckDB does not expose the size of data pages, which it chooses itself.
+    pub dictionary_page_size_limit: Option<u64>,
+    /// Version of the Parquet format.
+    pub parquet_version: ParquetVersion,
+    /// If false, columns are written without dictionary encoding.
+    pub dictionary_encoding: bool,
+    /// Target false positive ratio of the column bloom filters.
+    ///
+    /// DuckDB only writes bloom filters for the column chunks it dictionary-encodes, which
+    /// excludes chunks with too many distinct values and every chunk if `dictionary_encoding`
+    /// is false. Lower ratios give larger filters.
+    pub bloom_filter_false_positive_ratio: f64,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            codec: ParquetCodec::Zstd,
+            compression_level: None,
+            row_group_size: Some(DEFAULT_ROW_GROUP_SIZE),
+            dictionary_page_size_limit: None,
+            parquet_version: ParquetVersion::V2,
+            dictionary_encoding: true,
+            bloom_filter_false_positive_ratio: DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATIO,
+        }
+    }
+}
+
+/// Compression level used for Zstandard when [`WriteOptions::compression_level`] is `None`.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 22;
+
+/// Default number of rows per row group, half the DuckDB default.
+pub const DEFAULT_ROW_GROUP_SIZE: u64 = 61_440;
+
+/// Default false positive ratio of the column bloom filters.
+pub const DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATIO: f64 = 0.01;
+
+/// Builds the Parquet options of a DuckDB `COPY` statement from writer options.
+///
+/// # Arguments
+///
+/// * `options` - The writer options.
+///
+/// # Returns
+///
+/// * `String` - The comma-separated options, starting with `FORMAT PARQUET`.
+///
+/// # Errors
+///
+/// Returns an error if a compression level is given for a codec other than Zstandard, if the
+/// row group or dictionary page size is zero, or if the bloom filter false positive ratio is
+/// not between 0 and 1.
+pub fn build_parquet_options(options: &WriteOptions) -> Result<String, CottasError> {
+    let mut sql = vec![
+        "FORMAT PARQUET".to_string(),
+        format!("COMPRESSION {}", options.codec.sql_name()),
+    ];
+
+    match (options.codec, options.compression_level) {
+        (ParquetCodec::Zstd, level) => sql.push(format!(
+            "COMPRESSION_LEVEL {}",
+            level.unwrap_or(DEFAULT_ZSTD_LEVEL)
+        )),
+        (_, None) => {}
+        (codec, Some(_)) => {
+            return Err(CottasError::Unsupported(format!(
+                "compression level for codec {}",
+                codec.sql_name()
+            )));
+        }
+    }
+
+    let version = match options.parquet_version {
+        ParquetVersion::V1 => "V1",
+        ParquetVersion::V2 => "V2",
+    };
+    sql.push(format!("PARQUET_VERSION {}", version));
+
+    match options.row_group_size {
+        Some(0) => return Err(CottasError::Unsupported("row groups of 0 rows".to_string())),
+        Some(size) => sql.push(format!("ROW_GROUP_SIZE {}", size)),
+        None => {}
+    }
+    match options.dictionary_page_size_limit {
+        Some(0) => {
+            return Err(CottasError::Unsupported(
+                "dictionary pages of 0 bytes".to_string(),
+            ))
+        }
+        Some(size) => sql.push(format!("STRING_DICTIONARY_PAGE_SIZE_LIMIT {}", size)),
+        None => {}
+    }
+    if !options.dictionary_encoding {
+        sql.push("DICTIONARY_SIZE_LIMIT 0".to_string());
+    }
+
+    let ratio = options.bloom_filter_false_positive_ratio;
+    if !(ratio > 0.0 && ratio < 1.0) {
+        return Err(CottasError::Unsupported(format!(
+            "bloom filter false positive ratio {}",
+            ratio
+        )));
+    }
+    sql.push(format!("BLOOM_FILTER_FALSE_POSITIVE_RATIO {}", ratio));
+
+    Ok(sql.join(", "))
+}
+
+/// Quotes a value as an SQL string literal.
+///
+/// Every file path, term, or other user-supplied value interpolated into DuckDB SQL goes
+/// through this function. Single quotes are doubled, so the value cannot end the literal.
+///
+/// # Arguments
+///
+/// * `value` - The value to quote (e.g., a file path).
+///
+/// # Returns
+///
+/// * `String` - The SQL string literal, including the enclosing quotes.
+pub fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quotes a name as an SQL identifier (e.g., a column or a metadata key).
+///
+/// # Arguments
+///
+/// * `name` - The identifier to quote.
+///
+/// # Returns
+///
+/// * `String` - The quoted identifier. Double quotes in `name` are doubled.
+pub fn sql_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
 /// Builds an SQL ORDER BY clause from the given index string.
 ///
+/// The columns are sorted in the order they appear in the index, so `gspo` sorts graph-first.
+/// For 3-letter indexes in quad mode, the graph column is sorted last.
+///
 /// # Arguments
 ///
 /// * `index` - The index string (e.g., "spo", "gspo").
@@ -57,27 +354,90 @@ pub fn is_valid_index(index: &str) -> bool {
 ///
 /// * `String` - The ORDER BY clause for SQL queries.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the index is invalid.
-pub fn build_order_by(index: &str, quad_mode: bool) -> String {
-    if !is_valid_index(index) {
-        panic!("Invalid index: {}", index);
-    }
+/// Returns an error if the index is invalid or if a 4-letter index is used without `quad_mode`.
+pub fn build_order_by(index: &str, quad_mode: bool) -> Result<String, CottasError> {
+    check_index(index, quad_mode)?;
 
     let mut cols: Vec<&str> = index
+        .to_lowercase()
         .chars()
-        .filter_map(|c| match c {
-            's' => Some("s"),
-            'p' => Some("p"),
-            'o' => Some("o"),
-            _ => None,
+        .map(|c| match c {
+            's' => "s",
+            'p' => "p",
+            'o' => "o",
+            _ => "g",
         })
         .collect();
 
-    if quad_mode {
+    if quad_mode && cols.len() == 3 {
         cols.push("g");
     }
 
-    format!("ORDER BY {}", cols.join(", "))
+    Ok(format!("ORDER BY {}", cols.join(", ")))
+}
+
+/// Builds the DuckDB `KV_METADATA` option written to every Cottas file.
+///
+/// The metadata records the index, the COTTAS format version, the creating library and its
+/// version, the creation timestamp (RFC3339), and whether the file holds quads.
+///
+/// # Arguments
+///
+/// * `index` - The index string (e.g., "spo", "gspo").
+/// * `quad_mode` - If true, the file holds quads.
+///
+/// # Returns
+///
+/// * `String` - The `KV_METADATA {...}` option for a `COPY ... TO` statement.
+pub fn build_kv_metadata(index: &str, quad_mode: bool) -> String {
+    build_kv_metadata_inherited(index, quad_mode, &HashMap::new())
+}
+
+/// Builds the DuckDB `KV_METADATA` option, keeping the prefixes and base IRI of an existing
+/// file.
+///
+/// Entries in `inherited` that are also written by [`build_kv_metadata`] are replaced, and any
+/// other entry (e.g., the `ARROW:schema` written by other Parquet writers) is dropped.
+///
+/// # Arguments
+///
+/// * `index` - The index string (e.g., "spo", "gspo").
+/// * `quad_mode` - If true, the file holds quads.
+/// * `inherited` - Key-value metadata of the file the output derives from.
+///
+/// # Returns
+///
+/// * `String` - The `KV_METADATA {...}` option for a `COPY ... TO` statement.
+pub fn build_kv_metadata_inherited(
+    index: &str,
+    quad_mode: bool,
+    inherited: &HashMap<String, String>,
+) -> String {
+    let mut entries = BTreeMap::new();
+    for (key, value) in inherited {
+        if key == Namespaces::PREFIXES_KEY || key == Namespaces::BASE_KEY {
+            entries.insert(key.clone(), value.clone());
+        }
+    }
+    entries.insert("index".to_string(), index.to_lowercase());
+    entries.insert(
+        "format_version".to_string(),
+        COTTAS_FORMAT_VERSION.to_string(),
+    );
+    entries.insert(
+        "library_version".to_string(),
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    );
+    entries.insert("issued".to_string(), Utc::now().to_rfc3339());
+    entries.insert("quads".to_string(), quad_mode.to_string());
+
+    let entries = entries
+        .iter()
+        .map(|(key, value)| format!("{}: {}", sql_identifier(key), sql_string(value)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("KV_METADATA {{{}}}", entries)
 }
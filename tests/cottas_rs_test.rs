@@ -1,7 +1,7 @@
 use cottas_rs::*;
 use polars::prelude::*;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 #[test]
@@ -22,6 +22,82 @@ fn test_rdf2cottas() {
     println!("{:?}", df.head(Some(5)));
 }
 
+#[test]
+fn test_rdf2cottas_streaming_keeps_all_triples() {
+    let temp_dir = TempDir::new().unwrap();
+    let source_file = "tests/data/example3.ttl";
+    let target_file = temp_dir.path().join("example3.cottas");
+
+    rdf2_cottas(source_file, &target_file.to_string_lossy(), "spo").unwrap();
+
+    let expected = parse_rdf_quads(source_file).unwrap().count();
+    let file = fs::File::open(&target_file).unwrap();
+    let df = ParquetReader::new(file).finish().unwrap();
+
+    assert_eq!(df.height(), expected);
+}
+
+#[test]
+fn test_rdf2cottas_graph_first_index() {
+    let temp_dir = TempDir::new().unwrap();
+    let target_file = temp_dir.path().join("example_gspo.cottas");
+
+    rdf2_cottas(
+        "tests/data/example.nq",
+        &target_file.to_string_lossy(),
+        "gspo",
+    )
+    .unwrap();
+
+    let file = fs::File::open(&target_file).unwrap();
+    let df = ParquetReader::new(file).finish().unwrap();
+    let graphs: Vec<_> = df
+        .column("g")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+
+    let mut sorted = graphs.clone();
+    sorted.sort();
+    assert_eq!(graphs, sorted);
+}
+
+#[test]
+fn test_rdf2cottas_quad_index_on_triples() {
+    let temp_dir = TempDir::new().unwrap();
+    let target_file = temp_dir.path().join("example_gspo.cottas");
+
+    let result = rdf2_cottas(
+        "tests/data/example.ttl",
+        &target_file.to_string_lossy(),
+        "gspo",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_load_into_duckdb_row_count() {
+    let quads: Vec<_> = (0..200_000)
+        .map(|i| {
+            (
+                format!("<http://example.org/s{}>", i % 1000),
+                format!("<http://example.org/p{}>", i % 10),
+                format!("\"{}\"", i),
+                None,
+            )
+        })
+        .collect();
+
+    let conn = load_into_duckdb(&quads).unwrap();
+
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM quads", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count as usize, quads.len());
+}
+
 #[test]
 fn test_cottas2_rdf() {
     let cottas_file = "tests/data/example.cottas";
@@ -38,6 +114,204 @@ fn test_cottas2_rdf() {
     );
 }
 
+#[test]
+fn test_cottas2_rdf_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = "tests/data/example.cottas";
+    let expected = search(cottas_file, "?s ?p ?o").unwrap().len();
+
+    for extension in ["ttl", "rdf", "jsonld", "nt"] {
+        let rdf_file = temp_dir.path().join(format!("output.{}", extension));
+        let rdf_file = rdf_file.to_string_lossy();
+        cottas2_rdf(cottas_file, &rdf_file).unwrap();
+        assert_eq!(parse_rdf_quads(&rdf_file).unwrap().count(), expected);
+    }
+
+    let content = fs::read_to_string(temp_dir.path().join("output.rdf")).unwrap();
+    assert!(content.contains("rdf:RDF"));
+
+    // An explicit format overrides the extension
+    let rdf_file = temp_dir.path().join("output.txt");
+    cottas2_rdf_with_format(cottas_file, &rdf_file.to_string_lossy(), Some("turtle")).unwrap();
+    assert_eq!(
+        fs::read_to_string(&rdf_file).unwrap(),
+        fs::read_to_string(temp_dir.path().join("output.ttl")).unwrap()
+    );
+
+    let nquads_file = temp_dir.path().join("example.cottas");
+    rdf2_cottas(
+        "tests/data/example.nq",
+        &nquads_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    let trig_file = temp_dir.path().join("output.trig");
+    cottas2_rdf(&nquads_file.to_string_lossy(), &trig_file.to_string_lossy()).unwrap();
+    assert_eq!(
+        parse_rdf_quads(&trig_file.to_string_lossy())
+            .unwrap()
+            .count(),
+        parse_rdf_quads("tests/data/example.nq").unwrap().count()
+    );
+
+    // Failures before writing leave an existing output file untouched
+    let output = temp_dir.path().join("output.nt");
+    let output = output.to_string_lossy();
+    let content = fs::read_to_string(output.as_ref()).unwrap();
+    assert!(
+        cottas2_rdf_with_format(&nquads_file.to_string_lossy(), &output, Some("bogus")).is_err()
+    );
+    let missing = temp_dir.path().join("missing.cottas");
+    assert!(cottas2_rdf(&missing.to_string_lossy(), &output).is_err());
+    assert_eq!(fs::read_to_string(output.as_ref()).unwrap(), content);
+}
+
+#[test]
+fn test_cottas2_rdf_writer_compression() {
+    use std::io::Read;
+
+    let cottas_file = "tests/data/example.cottas";
+    let expected = search(cottas_file, "?s ?p ?o").unwrap().len();
+
+    let mut plain = Vec::new();
+    cottas2_rdf_writer(cottas_file, &mut plain, None, None).unwrap();
+    assert_eq!(
+        String::from_utf8(plain.clone()).unwrap().lines().count(),
+        expected
+    );
+
+    let mut gzip = Vec::new();
+    cottas2_rdf_writer(cottas_file, &mut gzip, None, Some(Compression::Gzip)).unwrap();
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(gzip.as_slice())
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain);
+
+    let temp_dir = TempDir::new().unwrap();
+    let rdf_file = temp_dir.path().join("output.nt.zst");
+    cottas2_rdf(cottas_file, &rdf_file.to_string_lossy()).unwrap();
+    let decoded = zstd::decode_all(fs::File::open(&rdf_file).unwrap()).unwrap();
+    assert_eq!(decoded, plain);
+}
+
+#[test]
+fn test_rdf2cottas_compressed_inputs() {
+    use std::io::Write;
+
+    let temp_dir = TempDir::new().unwrap();
+    let source = fs::read("tests/data/people.nt").unwrap();
+    let expected = parse_rdf_quads("tests/data/people.nt").unwrap().count();
+
+    for (extension, compression) in [
+        ("gz", Compression::Gzip),
+        ("zst", Compression::Zstd),
+        ("bz2", Compression::Bzip2),
+        ("xz", Compression::Xz),
+    ] {
+        let rdf_file = temp_dir.path().join(format!("people.nt.{}", extension));
+        let file = fs::File::create(&rdf_file).unwrap();
+        let mut writer = CompressedWriter::new(file, Some(compression)).unwrap();
+        writer.write_all(&source).unwrap();
+        writer.finish().unwrap();
+
+        let cottas_file = temp_dir.path().join(format!("people_{}.cottas", extension));
+        let cottas_file = cottas_file.to_string_lossy();
+        rdf2_cottas(&rdf_file.to_string_lossy(), &cottas_file, "spo").unwrap();
+        assert_eq!(search(&cottas_file, "?s ?p ?o").unwrap().len(), expected);
+    }
+
+    let cottas_file = temp_dir.path().join("people_reader.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+    rdf2_cottas_reader(std::io::Cursor::new(source), "nt", &cottas_file, "spo").unwrap();
+    assert_eq!(search(&cottas_file, "?s ?p ?o").unwrap().len(), expected);
+}
+
+#[test]
+fn test_prefixes_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("example.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+    rdf2_cottas("tests/data/example.ttl", &cottas_file, "spo").unwrap();
+
+    let rdf_file = temp_dir.path().join("output.ttl");
+    cottas2_rdf(&cottas_file, &rdf_file.to_string_lossy()).unwrap();
+    let content = fs::read_to_string(&rdf_file).unwrap();
+    assert!(content.contains("@prefix ex: <http://example.org/>"));
+    assert!(content.contains("ex:Alice ex:knows ex:Bob"));
+
+    let results = search(&cottas_file, "ex:Alice ex:knows ?o").unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0][2], "<http://example.org/Bob>");
+    assert!(search(&cottas_file, "?s foaf:name ?o").is_err());
+
+    let files = vec![cottas_file.to_string()];
+    let solutions = bgp(&files, "?a ex:knows ?b . ?b ex:knows ex:Alice").unwrap();
+    assert_eq!(solutions.len(), 1);
+    let results = query(&files, "ASK { ex:Alice ex:knows ex:Bob }").unwrap();
+    assert!(matches!(results, QueryResults::Boolean(true)));
+
+    // Files derived from the input keep its prefixes
+    let cat_file = temp_dir.path().join("cat.cottas");
+    let cat_file = cat_file.to_string_lossy();
+    cat(&files, &cat_file, None, None).unwrap();
+    let bob_file = temp_dir.path().join("bob.nt");
+    fs::write(
+        &bob_file,
+        "<http://example.org/Bob> <http://example.org/knows> <http://example.org/Charlie> .\n",
+    )
+    .unwrap();
+    let bob_cottas_file = temp_dir.path().join("bob.cottas");
+    let bob_cottas_file = bob_cottas_file.to_string_lossy();
+    rdf2_cottas(&bob_file.to_string_lossy(), &bob_cottas_file, "spo").unwrap();
+    let diff_file = temp_dir.path().join("diff.cottas");
+    let diff_file = diff_file.to_string_lossy();
+    diff(&cottas_file, &bob_cottas_file, &diff_file, None, None).unwrap();
+    for file in [&cat_file, &diff_file] {
+        let rdf_file = temp_dir.path().join("derived.ttl");
+        cottas2_rdf(file, &rdf_file.to_string_lossy()).unwrap();
+        let content = fs::read_to_string(&rdf_file).unwrap();
+        assert!(content.contains("@prefix ex: <http://example.org/>"));
+    }
+}
+
+#[test]
+fn test_search_prefixed_datatype() {
+    let mut namespaces = Namespaces::default();
+    namespaces.prefixes.insert(
+        "xsd".to_string(),
+        "http://www.w3.org/2001/XMLSchema#".to_string(),
+    );
+    let terms = parse_tp_with_namespaces("?s ?p \"1\"^^xsd:integer .", &namespaces).unwrap();
+    assert_eq!(
+        terms[2],
+        "\"1\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+    );
+
+    let temp_dir = TempDir::new().unwrap();
+    let rdf_file = temp_dir.path().join("ages.ttl");
+    fs::write(
+        &rdf_file,
+        "@prefix ex: <http://example.org/> .\n\
+         @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n\
+         ex:Alice ex:age 42 .\n\
+         ex:Bob ex:age \"42\" .\n",
+    )
+    .unwrap();
+    let cottas_file = temp_dir.path().join("ages.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+    rdf2_cottas(&rdf_file.to_string_lossy(), &cottas_file, "spo").unwrap();
+
+    let results = search(&cottas_file, "?s ex:age \"42\"^^xsd:integer").unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0][0], "<http://example.org/Alice>");
+    assert_eq!(
+        results[0][2],
+        "\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+    );
+    assert!(search(&cottas_file, "?s ?p \"42\"^^foaf:integer").is_err());
+}
+
 #[test]
 fn test_search_all_triples() {
     let cottas_file = "tests/data/example.cottas";
@@ -82,6 +356,41 @@ fn test_search_by_predicate() {
     }
 }
 
+#[test]
+fn test_search_quads_typed() {
+    let cottas_file = "tests/data/example.cottas";
+    let pattern = "?s <http://example.org/knows> ?o";
+
+    let quads = search_quads(cottas_file, pattern).unwrap();
+    assert_eq!(quads.len(), 3);
+
+    let knows = oxrdf::NamedNode::new("http://example.org/knows").unwrap();
+    for quad in &quads {
+        assert_eq!(quad.predicate, knows);
+        assert!(quad.graph_name.is_default_graph());
+    }
+
+    let triples = search_triples(cottas_file, pattern).unwrap();
+    assert_eq!(triples.len(), 3);
+}
+
+#[test]
+fn test_search_iter_stops_early() {
+    let cottas_file = "tests/data/example.cottas";
+
+    let mut rows = search_iter(cottas_file, "?s ?p ?o").unwrap();
+    let first = rows.next().unwrap().unwrap();
+    assert_eq!(first.len(), 3);
+    drop(rows);
+
+    let streamed: Vec<_> = search_iter(cottas_file, "?s <http://example.org/knows> ?o")
+        .unwrap()
+        .take(2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(streamed.len(), 2);
+}
+
 #[test]
 fn test_search_specific_subject() {
     let cottas_file = "tests/data/example.cottas";
@@ -150,6 +459,666 @@ fn test_search_exact_triple() {
     assert_eq!(results[0][2], "<http://example.org/Charlie>");
 }
 
+#[test]
+fn test_parse_tp_terms() {
+    use cottas_rs::parser::parse_tp;
+
+    assert_eq!(
+        parse_tp("<http://ex/a> <http://ex/p> \"see <http://ex/a>\"").unwrap(),
+        ["<http://ex/a>", "<http://ex/p>", "\"see <http://ex/a>\""]
+    );
+    assert_eq!(
+        parse_tp("?s ?p \"a b\"@en <http://ex/g>").unwrap(),
+        ["?s", "?p", "\"a b\"@en", "<http://ex/g>"]
+    );
+    assert_eq!(
+        parse_tp("_:b0 ?p \"say \\\"hi\\\"\"^^<http://ex/dt> .").unwrap(),
+        ["_:b0", "?p", "\"say \\\"hi\\\"\"^^<http://ex/dt>"]
+    );
+    assert_eq!(
+        parse_tp("?s ?p \"x\"^^<http://www.w3.org/2001/XMLSchema#string>").unwrap(),
+        ["?s", "?p", "\"x\""]
+    );
+
+    assert!(parse_tp("?s ?p").is_err());
+    assert!(parse_tp("?s ?p \"open").is_err());
+    assert!(parse_tp("?s <http://ex/p ?o").is_err());
+}
+
+#[test]
+fn test_search_repeated_variable() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("self_loops.cottas");
+    rdf2_cottas(
+        "tests/data/self_loops.nt",
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+
+    let results = search(
+        &cottas_file.to_string_lossy(),
+        "?x <http://example.org/knows> ?x",
+    )
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0][0], results[0][2]);
+
+    let bindings = search_bindings(&cottas_file.to_string_lossy(), "?s ?p ?s").unwrap();
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[0].len(), 2);
+    assert_eq!(
+        bindings[0]["s"],
+        oxrdf::Term::from(oxrdf::NamedNode::new("http://example.org/Alice").unwrap())
+    );
+}
+
+#[test]
+fn test_bgp_join() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("people.cottas");
+    rdf2_cottas(
+        "tests/data/people.nt",
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+
+    let files = [cottas_file.to_string_lossy().to_string()];
+    let mut names: Vec<_> = bgp(
+        &files,
+        "?p a <http://example.org/Person> . ?p <http://example.org/name> ?n .",
+    )
+    .unwrap()
+    .into_iter()
+    .map(|solution| {
+        assert_eq!(solution.len(), 2);
+        solution["n"].to_string()
+    })
+    .collect();
+    names.sort();
+
+    assert_eq!(names, ["\"Alice\"", "\"Bob\"@en"]);
+
+    assert!(bgp(&files, "?p a").is_err());
+}
+
+#[test]
+fn test_sparql_query() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("people.cottas");
+    rdf2_cottas(
+        "tests/data/people.nt",
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    let files = [cottas_file.to_string_lossy().to_string()];
+
+    let results = query(
+        &files,
+        "PREFIX ex: <http://example.org/>
+         SELECT ?s ?n WHERE { ?s a ?type OPTIONAL { ?s ex:name ?n FILTER(lang(?n) = 'en') } }
+         ORDER BY ?s",
+    )
+    .unwrap();
+    let QueryResults::Solutions { solutions, .. } = results else {
+        panic!("expected solutions");
+    };
+    let names: Vec<_> = solutions
+        .iter()
+        .map(|solution| solution[1].as_ref().map(|term| term.to_string()))
+        .collect();
+    assert_eq!(names, [None, Some("\"Bob\"@en".to_string()), None]);
+
+    let results = query(
+        &files,
+        "SELECT ?type (COUNT(?s) AS ?count) WHERE { ?s a ?type } GROUP BY ?type ORDER BY DESC(?count) LIMIT 1",
+    )
+    .unwrap();
+    let mut json = Vec::new();
+    write_query_results(&results, QueryResultsFormat::Json, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains("http://example.org/Person"));
+    assert!(!json.contains("http://example.org/Dog"));
+
+    let results = query(
+        &files,
+        "ASK { <http://example.org/Rex> a <http://example.org/Person> }",
+    )
+    .unwrap();
+    assert!(matches!(results, QueryResults::Boolean(false)));
+
+    assert!(query(&files, "DESCRIBE <http://example.org/Rex>").is_err());
+}
+
+/// Writes a Cottas file with `count` people, each with a name, an age unless their number is
+/// a multiple of 3, and a link to the next person.
+fn people_cottas(temp_dir: &TempDir, count: usize) -> String {
+    let rdf_file = temp_dir.path().join("many_people.nt");
+    let triples: String = (0..count)
+        .map(|i| {
+            let mut triples = format!(
+                "<http://ex/s{i}> <http://ex/name> \"n{i}\" .\n\
+                 <http://ex/s{i}> <http://ex/knows> <http://ex/s{}> .\n",
+                i + 1
+            );
+            if i % 3 != 0 {
+                triples.push_str(&format!(
+                    "<http://ex/s{i}> <http://ex/age> \"{i}\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n"
+                ));
+            }
+            triples
+        })
+        .collect();
+    fs::write(&rdf_file, triples).unwrap();
+
+    let cottas_file = temp_dir.path().join("many_people.cottas");
+    rdf2_cottas(
+        &rdf_file.to_string_lossy(),
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    cottas_file.to_string_lossy().to_string()
+}
+
+/// Runs a SELECT query and returns its solutions as N-Triples strings.
+fn select(files: &[String], sparql: &str) -> Vec<Vec<Option<String>>> {
+    let QueryResults::Solutions { solutions, .. } = query(files, sparql).unwrap() else {
+        panic!("expected solutions");
+    };
+    solutions
+        .iter()
+        .map(|solution| {
+            solution
+                .iter()
+                .map(|term| term.as_ref().map(|term| term.to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_sparql_join() {
+    let temp_dir = TempDir::new().unwrap();
+    let files = [people_cottas(&temp_dir, 3000)];
+
+    let count = select(
+        &files,
+        "SELECT (COUNT(*) AS ?c) WHERE { { ?s <http://ex/name> ?n } { ?s <http://ex/age> ?a } }",
+    );
+    assert_eq!(
+        count[0][0].as_deref(),
+        Some("\"2000\"^^<http://www.w3.org/2001/XMLSchema#integer>")
+    );
+
+    // ?s is bound on both sides, although ?a is optional on the left side
+    let solutions = select(
+        &files,
+        "SELECT ?s WHERE {
+            { ?s <http://ex/name> ?n OPTIONAL { ?s <http://ex/age> ?a } }
+            { ?t <http://ex/knows> ?s }
+        }",
+    );
+    assert_eq!(solutions.len(), 2999);
+}
+
+#[test]
+fn test_sparql_optional() {
+    let temp_dir = TempDir::new().unwrap();
+    let files = [people_cottas(&temp_dir, 3000)];
+
+    let solutions = select(
+        &files,
+        "SELECT ?s ?a WHERE { ?s <http://ex/name> ?n OPTIONAL { ?s <http://ex/age> ?a } }",
+    );
+    assert_eq!(solutions.len(), 3000);
+    assert_eq!(solutions.iter().filter(|s| s[1].is_none()).count(), 1000);
+
+    // An unbound ?a is compatible with any value of ?a
+    let solutions = select(
+        &files,
+        "SELECT ?s ?a WHERE {
+            { ?s <http://ex/name> ?n OPTIONAL { ?s <http://ex/age> ?a } }
+            VALUES ?a { 5 }
+        }",
+    );
+    assert_eq!(solutions.len(), 1001);
+    assert!(solutions
+        .iter()
+        .all(|s| s[1].as_deref() == Some("\"5\"^^<http://www.w3.org/2001/XMLSchema#integer>")));
+}
+
+/// Writes a Cottas file whose subjects `<http://ex/a>` to `<http://ex/f>` have, in order, a
+/// blank node, an IRI, the integers 10 and 9, a string, and no value for `<http://ex/v>`.
+fn ordering_cottas(temp_dir: &TempDir) -> String {
+    let rdf_file = temp_dir.path().join("ordering.nt");
+    fs::write(
+        &rdf_file,
+        "<http://ex/a> <http://ex/v> _:b .\n\
+         <http://ex/b> <http://ex/v> <http://ex/x> .\n\
+         <http://ex/c> <http://ex/v> \"10\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n\
+         <http://ex/d> <http://ex/v> \"9\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n\
+         <http://ex/e> <http://ex/v> \"word\" .\n\
+         <http://ex/f> <http://ex/w> \"other\" .\n",
+    )
+    .unwrap();
+
+    let cottas_file = temp_dir.path().join("ordering.cottas");
+    rdf2_cottas(
+        &rdf_file.to_string_lossy(),
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    cottas_file.to_string_lossy().to_string()
+}
+
+#[test]
+fn test_sparql_order_by() {
+    let temp_dir = TempDir::new().unwrap();
+    let files = [ordering_cottas(&temp_dir)];
+    let subjects = |sparql: &str| -> Vec<String> {
+        select(&files, sparql)
+            .into_iter()
+            .map(|solution| solution[0].clone().unwrap())
+            .collect()
+    };
+
+    // Unbound values, blank nodes, IRIs, then literals
+    let ascending =
+        subjects("SELECT ?s WHERE { ?s ?p ?o OPTIONAL { ?s <http://ex/v> ?v } } ORDER BY ?v");
+    assert_eq!(
+        ascending,
+        [
+            "<http://ex/f>",
+            "<http://ex/a>",
+            "<http://ex/b>",
+            "<http://ex/e>",
+            "<http://ex/d>",
+            "<http://ex/c>"
+        ]
+    );
+
+    // DESC is the exact reverse, with unbound values last
+    let mut descending =
+        subjects("SELECT ?s WHERE { ?s ?p ?o OPTIONAL { ?s <http://ex/v> ?v } } ORDER BY DESC(?v)");
+    descending.reverse();
+    assert_eq!(descending, ascending);
+
+    // DISTINCT keeps the order of the first occurrence, even by a variable that is not projected
+    assert_eq!(
+        subjects("SELECT DISTINCT ?p WHERE { ?s ?p ?o } ORDER BY ?s"),
+        ["<http://ex/v>", "<http://ex/w>"]
+    );
+    assert_eq!(
+        subjects("SELECT DISTINCT ?p WHERE { ?s ?p ?o } ORDER BY DESC(?s)"),
+        ["<http://ex/w>", "<http://ex/v>"]
+    );
+    assert_eq!(
+        subjects("SELECT DISTINCT ?p WHERE { ?s ?p ?o } ORDER BY DESC(?s) LIMIT 1"),
+        ["<http://ex/w>"]
+    );
+}
+
+#[test]
+fn test_sparql_escaped_literals() {
+    let temp_dir = TempDir::new().unwrap();
+    let rdf_file = temp_dir.path().join("escaped.nt");
+    fs::write(
+        &rdf_file,
+        "<http://ex/s> <http://ex/text> \"tab\\tline\\nquote\\\"\\u00E9\\U0001F600\\\\u0041\" .\n",
+    )
+    .unwrap();
+    let cottas_file = temp_dir.path().join("escaped.cottas");
+    rdf2_cottas(
+        &rdf_file.to_string_lossy(),
+        &cottas_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    let files = [cottas_file.to_string_lossy().to_string()];
+
+    // The lexical form is decoded, while an escaped backslash stays a backslash
+    let solutions = select(
+        &files,
+        "SELECT ?s WHERE { ?s <http://ex/text> ?t FILTER(STR(?t) = \"tab\\tline\\nquote\\\"\u{e9}\u{1F600}\\\\u0041\") }",
+    );
+    assert_eq!(solutions, [[Some("<http://ex/s>".to_string())]]);
+
+    let solutions = select(
+        &files,
+        "SELECT (STRLEN(?t) AS ?n) WHERE { ?s <http://ex/text> ?t }",
+    );
+    assert_eq!(
+        solutions[0][0].as_deref(),
+        Some("\"23\"^^<http://www.w3.org/2001/XMLSchema#integer>")
+    );
+}
+
+#[test]
+fn test_sparql_graph_on_triples() {
+    let temp_dir = TempDir::new().unwrap();
+    let files = [ordering_cottas(&temp_dir)];
+
+    // A file of triples has no named graphs
+    assert!(select(&files, "SELECT ?g ?s WHERE { GRAPH ?g { ?s ?p ?o } }").is_empty());
+    assert!(select(
+        &files,
+        "SELECT ?s WHERE { GRAPH <http://ex/g> { ?s ?p ?o } }"
+    )
+    .is_empty());
+    assert!(matches!(
+        query(&files, "ASK { GRAPH ?g { ?s ?p ?o } }").unwrap(),
+        QueryResults::Boolean(false)
+    ));
+}
+
+#[test]
+fn test_negotiate_results_format() {
+    assert_eq!(
+        negotiate_results_format(None),
+        Some(QueryResultsFormat::Json)
+    );
+    assert_eq!(
+        negotiate_results_format(Some("text/csv;q=0.5, application/sparql-results+xml")),
+        Some(QueryResultsFormat::Xml)
+    );
+    assert_eq!(
+        negotiate_results_format(Some("text/html, */*;q=0.1")),
+        Some(QueryResultsFormat::Json)
+    );
+    assert_eq!(negotiate_results_format(Some("image/png")), None);
+
+    assert_eq!(
+        negotiate_rdf_format(Some("text/turtle, application/n-quads;q=0.8")),
+        Some(oxrdfio::RdfFormat::NQuads)
+    );
+    assert_eq!(negotiate_rdf_format(Some("text/turtle")), None);
+
+    assert_eq!(
+        negotiate_graph_format(None),
+        Some(oxrdfio::RdfFormat::NTriples)
+    );
+    assert_eq!(
+        negotiate_graph_format(Some("application/n-triples;q=0.5, application/rdf+xml")),
+        Some(oxrdfio::RdfFormat::RdfXml)
+    );
+    assert_eq!(
+        negotiate_graph_format(Some("application/sparql-results+json")),
+        None
+    );
+}
+
+/// Sends a raw HTTP request to a server and returns its response.
+fn request_response(address: &str, request: &[u8]) -> String {
+    use std::io::{Read, Write};
+
+    let mut stream = (0..50)
+        .find_map(|_| {
+            std::net::TcpStream::connect(address)
+                .map_err(|_| std::thread::sleep(std::time::Duration::from_millis(100)))
+                .ok()
+        })
+        .expect("the server should start");
+    // The server may answer and close the connection before the whole body is sent
+    let _ = stream.write_all(request);
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+/// Sends a raw HTTP request to a server and returns the status line of its response.
+fn request_status(address: &str, request: &[u8]) -> String {
+    request_response(address, request)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[test]
+fn test_sparql_endpoint_body_limit() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let server_address = address.clone();
+    std::thread::spawn(move || serve(&["unused.cottas".to_string()], &server_address).unwrap());
+
+    // A too large Content-Length is rejected before the body is read
+    let request = format!(
+        "POST {SPARQL_PATH} HTTP/1.1\r\nHost: {address}\r\n\
+         Content-Type: application/sparql-query\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        MAX_QUERY_BYTES + 1
+    );
+    assert!(request_status(&address, request.as_bytes()).starts_with("HTTP/1.1 413"));
+
+    // A chunked body is only read up to the limit
+    let chunk = "#".repeat(MAX_QUERY_BYTES as usize + 1);
+    let request = format!(
+        "POST {SPARQL_PATH} HTTP/1.1\r\nHost: {address}\r\n\
+         Content-Type: application/sparql-query\r\nTransfer-Encoding: chunked\r\n\
+         Connection: close\r\n\r\n{:x}\r\n{chunk}\r\n0\r\n\r\n",
+        chunk.len()
+    );
+    assert!(request_status(&address, request.as_bytes()).starts_with("HTTP/1.1 413"));
+}
+
+#[test]
+fn test_sparql_endpoint_negotiation() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let server_address = address.clone();
+    std::thread::spawn(move || {
+        serve(&["tests/data/example.cottas".to_string()], &server_address).unwrap()
+    });
+    let get = |query: &str, accept: &str| {
+        let query = form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>();
+        let request = format!(
+            "GET {SPARQL_PATH}?query={query} HTTP/1.1\r\nHost: {address}\r\n\
+             Accept: {accept}\r\nConnection: close\r\n\r\n"
+        );
+        request_response(&address, request.as_bytes())
+    };
+
+    let construct = "CONSTRUCT { ?s ?p ?o } WHERE { ?s ?p ?o }";
+    let response = get(construct, "application/rdf+xml");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("Content-Type: application/rdf+xml"));
+    assert!(response.contains("<rdf:RDF"));
+    let response = get(construct, "application/sparql-results+json");
+    assert!(response.starts_with("HTTP/1.1 406"));
+
+    let response = get("SELECT * WHERE { ?s ?p ?o }", "application/n-triples");
+    assert!(response.starts_with("HTTP/1.1 406"));
+    // DESCRIBE is unsupported, so a 406 shows that the query was not evaluated
+    let response = get("DESCRIBE <http://example.org/a>", "image/png");
+    assert!(response.starts_with("HTTP/1.1 406"));
+}
+
+/// Starts a Triple Pattern Fragments server over a Cottas file and returns its address.
+fn spawn_tpf_server(cottas_file: &str) -> String {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let (path, server_address) = (cottas_file.to_string(), address.clone());
+    std::thread::spawn(move || serve_tpf(&path, &server_address).unwrap());
+    address
+}
+
+/// Requests a fragment as N-Quads and returns the data quads and the metadata quads.
+fn get_fragment(address: &str, target: &str) -> (Vec<String>, Vec<String>) {
+    use std::io::{Read, Write};
+
+    let mut stream = (0..50)
+        .find_map(|_| {
+            std::net::TcpStream::connect(address)
+                .map_err(|_| std::thread::sleep(std::time::Duration::from_millis(100)))
+                .ok()
+        })
+        .expect("the server should start");
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: application/n-quads\r\nConnection: close\r\n\r\n",
+        target, address
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+    body.lines()
+        .map(str::to_string)
+        .partition(|line| !line.contains("#metadata> ."))
+}
+
+/// Returns the objects of the metadata quads with the given Hydra property.
+fn hydra_values(metadata: &[String], property: &str) -> Vec<String> {
+    let property = format!("<http://www.w3.org/ns/hydra/core#{}> ", property);
+    metadata
+        .iter()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(&property)?;
+            Some(rest.split(' ').next()?.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn test_tpf_pages() {
+    let temp_dir = TempDir::new().unwrap();
+    let people = temp_dir.path().join("people.cottas");
+    let people = people.to_string_lossy();
+    rdf2_cottas("tests/data/people.nt", &people, "spo").unwrap();
+
+    let address = spawn_tpf_server(&people);
+    let total_items = "\"6\"^^<http://www.w3.org/2001/XMLSchema#integer>".to_string();
+
+    let (quads, metadata) = get_fragment(&address, "/fragments");
+    assert_eq!(quads.len(), 6);
+    assert!(hydra_values(&metadata, "next").is_empty());
+    assert_eq!(
+        hydra_values(&metadata, "totalItems"),
+        vec![total_items.clone()]
+    );
+
+    // A page past the last match is empty but keeps the exact count
+    let (quads, metadata) = get_fragment(&address, "/fragments?page=2");
+    assert!(quads.is_empty());
+    assert!(hydra_values(&metadata, "next").is_empty());
+    assert_eq!(hydra_values(&metadata, "totalItems"), vec![total_items]);
+    assert_eq!(
+        hydra_values(&metadata, "previous"),
+        vec![format!("<http://{}/fragments>", address)]
+    );
+
+    // People in 20 graphs and a chain of 200 triples in the default graph
+    let mut nquads = String::new();
+    let people_triples = fs::read_to_string("tests/data/people.nt").unwrap();
+    for graph in 0..20 {
+        for line in people_triples.lines() {
+            let triple = line.trim_end().trim_end_matches('.');
+            nquads.push_str(&format!("{}<http://example.org/g{}> .\n", triple, graph));
+        }
+    }
+    for i in 0..200 {
+        nquads.push_str(&format!(
+            "<http://example.org/n{}> <http://example.org/next> <http://example.org/n{}> .\n",
+            i,
+            i + 1
+        ));
+    }
+    let rdf_file = temp_dir.path().join("graphs.nq");
+    fs::write(&rdf_file, nquads).unwrap();
+    let graphs = temp_dir.path().join("graphs.cottas");
+    let graphs = graphs.to_string_lossy();
+    rdf2_cottas(&rdf_file.to_string_lossy(), &graphs, "spog").unwrap();
+
+    let address = spawn_tpf_server(&graphs);
+
+    // Without a graph selector, every triple is returned once across pages
+    let mut triples = Vec::new();
+    let mut target = "/fragments".to_string();
+    for page in 1..=3 {
+        let (quads, metadata) = get_fragment(&address, &target);
+        assert_eq!(quads.len(), if page < 3 { 100 } else { 6 });
+        triples.extend(quads);
+
+        let next = hydra_values(&metadata, "next");
+        if page < 3 {
+            assert_eq!(
+                next,
+                vec![format!("<http://{}/fragments?page={}>", address, page + 1)]
+            );
+            target = format!("/fragments?page={}", page + 1);
+        } else {
+            assert!(next.is_empty());
+            assert_eq!(
+                hydra_values(&metadata, "totalItems"),
+                vec!["\"206\"^^<http://www.w3.org/2001/XMLSchema#integer>".to_string()]
+            );
+        }
+    }
+    let mut distinct = triples.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), 206);
+    // Pages follow the spo index
+    assert_eq!(triples, {
+        let mut sorted = triples.clone();
+        sorted.sort();
+        sorted
+    });
+
+    let (quads, _) = get_fragment(&address, "/fragments?g=http%3A%2F%2Fexample.org%2Fg0");
+    assert_eq!(quads.len(), 6);
+    assert!(quads
+        .iter()
+        .all(|q| q.ends_with("<http://example.org/g0> .")));
+
+    // Simultaneous requests are all answered
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let address = address.clone();
+            std::thread::spawn(move || get_fragment(&address, "/fragments").0.len())
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 100);
+    }
+}
+
+#[test]
+fn test_estimate_count() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("people.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+    rdf2_cottas("tests/data/people.nt", &cottas_file, "spo").unwrap();
+
+    assert_eq!(
+        estimate_count_in_duckdb(&cottas_file, "?s ?p ?o").unwrap(),
+        6
+    );
+
+    let pattern = "?s <http://example.org/name> ?o";
+    let exact = search(&cottas_file, pattern).unwrap().len() as u64;
+    let estimate = estimate_count_in_duckdb(&cottas_file, pattern).unwrap();
+    assert!(estimate >= exact);
+
+    let pattern = "<http://example.org/Zoe> ?p ?o";
+    assert_eq!(estimate_count_in_duckdb(&cottas_file, pattern).unwrap(), 0);
+}
+
 #[test]
 fn test_search_no_results() {
     let cottas_file = "tests/data/example.cottas";
@@ -231,6 +1200,69 @@ fn test_cat_remove_input_files() {
     assert!(output_file.exists());
 }
 
+#[test]
+fn test_cat_mixed_triples_and_quads() {
+    let temp_dir = TempDir::new().unwrap();
+    let quads_file = temp_dir.path().join("quads.cottas");
+    rdf2_cottas(
+        "tests/data/example.nq",
+        &quads_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+
+    // A file name with characters that must be escaped in an IRI
+    let triples_file = temp_dir.path().join("odd <name> {1}#%|^.cottas");
+    fs::copy("tests/data/example.cottas", &triples_file).unwrap();
+    let input_files = [
+        triples_file.to_string_lossy().to_string(),
+        quads_file.to_string_lossy().to_string(),
+    ];
+
+    let default_file = temp_dir.path().join("default.cottas");
+    cat_with_graph_mode(
+        &input_files,
+        &default_file.to_string_lossy(),
+        Some("gspo"),
+        Some(false),
+        MixedGraphMode::DefaultGraph,
+    )
+    .unwrap();
+
+    let df = ParquetReader::new(fs::File::open(&default_file).unwrap())
+        .finish()
+        .unwrap();
+    let g = df.column("g").unwrap();
+    assert!(g.null_count() > 0);
+    assert!(g.null_count() < df.height());
+
+    let named_file = temp_dir.path().join("named.cottas");
+    cat_with_graph_mode(
+        &input_files,
+        &named_file.to_string_lossy(),
+        None,
+        Some(false),
+        MixedGraphMode::FileGraph,
+    )
+    .unwrap();
+
+    let df = ParquetReader::new(fs::File::open(&named_file).unwrap())
+        .finish()
+        .unwrap();
+    let g = df.column("g").unwrap();
+    assert_eq!(g.null_count(), 0);
+    let graph = g
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .find(|graph| graph.starts_with("<file://"))
+        .unwrap()
+        .to_string();
+    assert!(graph.ends_with("/odd%20%3Cname%3E%20%7B1%7D%23%25%7C%5E.cottas>"));
+    let iri = graph.trim_start_matches('<').trim_end_matches('>');
+    assert!(oxrdf::NamedNode::new(iri).is_ok());
+}
+
 #[test]
 fn test_diff_cottas() {
     let source_file1 = "tests/data/example1.ttl";
@@ -266,6 +1298,425 @@ fn test_diff_cottas() {
     fs::remove_file(output_file).ok();
 }
 
+#[test]
+fn test_diff_mixed_triples_and_quads() {
+    let temp_dir = TempDir::new().unwrap();
+    let triples_file = temp_dir.path().join("triples.cottas");
+    fs::copy("tests/data/example.cottas", &triples_file).unwrap();
+    let quads_file = temp_dir.path().join("quads.cottas");
+    rdf2_cottas(
+        "tests/data/example.nq",
+        &quads_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    let triples_file = triples_file.to_string_lossy();
+    let quads_file = quads_file.to_string_lossy();
+
+    // Triples are read with a NULL graph, so none of them is in a named graph of the quads
+    let triples_diff = temp_dir.path().join("triples_diff.cottas");
+    diff(
+        &triples_file,
+        &quads_file,
+        &triples_diff.to_string_lossy(),
+        None,
+        None,
+    )
+    .unwrap();
+    let df = ParquetReader::new(fs::File::open(&triples_diff).unwrap())
+        .finish()
+        .unwrap();
+    assert!(df.height() > 0);
+    assert_eq!(df.column("g").unwrap().null_count(), df.height());
+
+    let quads_diff = temp_dir.path().join("quads_diff.cottas");
+    diff(
+        &quads_file,
+        &triples_file,
+        &quads_diff.to_string_lossy(),
+        None,
+        None,
+    )
+    .unwrap();
+    let df = ParquetReader::new(fs::File::open(&quads_diff).unwrap())
+        .finish()
+        .unwrap();
+    assert!(df.height() > 0);
+    assert!(df.column("g").unwrap().null_count() < df.height());
+}
+
+/// Writes `tests/data/example1.ttl` and `tests/data/example2.ttl` as two versions of a Cottas
+/// file in a new temporary directory, the first one with the given index.
+fn example_versions(old_index: &str) -> (TempDir, PathBuf, PathBuf) {
+    let temp_dir = TempDir::new().unwrap();
+    let old_file = temp_dir.path().join("example1.cottas");
+    let new_file = temp_dir.path().join("example2.cottas");
+    rdf2_cottas(
+        "tests/data/example1.ttl",
+        &old_file.to_string_lossy(),
+        old_index,
+    )
+    .unwrap();
+    rdf2_cottas(
+        "tests/data/example2.ttl",
+        &new_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    (temp_dir, old_file, new_file)
+}
+
+#[test]
+fn test_intersect_and_symdiff_cottas() {
+    let (temp_dir, file1, file2) = example_versions("spo");
+
+    let intersect_file = temp_dir.path().join("intersect.cottas");
+    intersect(
+        &file1.to_string_lossy(),
+        &file2.to_string_lossy(),
+        &intersect_file.to_string_lossy(),
+        Some("spo"),
+        Some(false),
+    )
+    .unwrap();
+    let shared = search(&intersect_file.to_string_lossy(), "?s ?p ?o").unwrap();
+    assert_eq!(shared.len(), 2);
+
+    let symdiff_file = temp_dir.path().join("symdiff.cottas");
+    symdiff(
+        &file1.to_string_lossy(),
+        &file2.to_string_lossy(),
+        &symdiff_file.to_string_lossy(),
+        Some("spo"),
+        Some(true),
+    )
+    .unwrap();
+    let differing = search(&symdiff_file.to_string_lossy(), "?s ?p ?o").unwrap();
+    assert_eq!(differing.len(), 2);
+    assert_eq!(differing[0][0], "<http://example.org/Pepito>");
+    assert_eq!(differing[1][0], "<http://example.org/Stephanya>");
+
+    assert!(!file1.exists());
+    assert!(!file2.exists());
+}
+
+#[test]
+fn test_changeset_cottas() {
+    let (temp_dir, old_file, new_file) = example_versions("spo");
+
+    let added_file = temp_dir.path().join("added.cottas");
+    let removed_file = temp_dir.path().join("removed.cottas");
+    let summary = changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::Cottas {
+            added: &added_file.to_string_lossy(),
+            removed: &removed_file.to_string_lossy(),
+        },
+        None,
+        Some(true),
+    )
+    .unwrap();
+
+    assert_eq!(summary.added, 1);
+    assert_eq!(summary.removed, 1);
+    let graphs = summary.graphs.unwrap();
+    assert_eq!(graphs.len(), 1);
+    assert!(graphs[0].graph.is_none());
+
+    let added = search(&added_file.to_string_lossy(), "?s ?p ?o").unwrap();
+    assert_eq!(added[0][0], "<http://example.org/Pepito>");
+    let removed = search(&removed_file.to_string_lossy(), "?s ?p ?o").unwrap();
+    assert_eq!(removed[0][0], "<http://example.org/Stephanya>");
+}
+
+#[test]
+fn test_changeset_rdf_patch() {
+    let (temp_dir, old_file, new_file) = example_versions("spo");
+
+    let patch_file = temp_dir.path().join("changes.rdfp");
+    changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::RdfPatch(&patch_file.to_string_lossy()),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let patch = fs::read_to_string(&patch_file).unwrap();
+    let lines: Vec<_> = patch.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "TX .",
+            "D <http://example.org/Stephanya> <http://example.org/knows> <http://example.org/Simon> .",
+            "A <http://example.org/Pepito> <http://example.org/knows> <http://example.org/Pepe> .",
+            "TC .",
+        ]
+    );
+
+    // Any sink can receive the document, and outputs are compressed like RDF files
+    let mut written = Vec::new();
+    let summary = changeset_writer(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetFormat::RdfPatch,
+        &mut written,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!((summary.added, summary.removed), (1, 1));
+    assert_eq!(String::from_utf8(written).unwrap(), patch);
+
+    let gzip_file = temp_dir.path().join("changes.rdfp.gz");
+    changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::RdfPatch(&gzip_file.to_string_lossy()),
+        None,
+        None,
+    )
+    .unwrap();
+    let mut decoded = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::GzDecoder::new(fs::File::open(&gzip_file).unwrap()),
+        &mut decoded,
+    )
+    .unwrap();
+    assert_eq!(decoded, patch);
+}
+
+#[test]
+fn test_changeset_sparql_update() {
+    let (temp_dir, old_file, new_file) = example_versions("spo");
+
+    let update_file = temp_dir.path().join("changes.ru");
+    changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::SparqlUpdate(&update_file.to_string_lossy()),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let update = fs::read_to_string(&update_file).unwrap();
+    assert!(update.starts_with("DELETE DATA {"));
+    assert!(update.contains("<http://example.org/Stephanya>"));
+    assert!(update.contains("INSERT DATA {"));
+}
+
+#[test]
+fn test_changeset_sparql_update_blank_nodes() {
+    let temp_dir = TempDir::new().unwrap();
+    let old_rdf = temp_dir.path().join("old.nt");
+    let new_rdf = temp_dir.path().join("new.nt");
+    fs::write(&old_rdf, "_:b <http://ex/p> <http://ex/o> .\n").unwrap();
+    fs::write(&new_rdf, "<http://ex/s> <http://ex/p> <http://ex/o> .\n").unwrap();
+
+    let old_file = temp_dir.path().join("old.cottas");
+    let new_file = temp_dir.path().join("new.cottas");
+    rdf2_cottas(
+        &old_rdf.to_string_lossy(),
+        &old_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+    rdf2_cottas(
+        &new_rdf.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        "spo",
+    )
+    .unwrap();
+
+    // DELETE DATA cannot hold blank nodes
+    let update_file = temp_dir.path().join("changes.ru");
+    let result = changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::SparqlUpdate(&update_file.to_string_lossy()),
+        None,
+        None,
+    );
+    assert!(matches!(result, Err(CottasError::Unsupported(_))));
+    assert!(!update_file.exists());
+}
+
+#[test]
+fn test_patch_roundtrip() {
+    let (temp_dir, old_file, new_file) = example_versions("pos");
+
+    let added_file = temp_dir.path().join("added.cottas");
+    let removed_file = temp_dir.path().join("removed.cottas");
+    let patch_file = temp_dir.path().join("changes.rdfp");
+    changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::Cottas {
+            added: &added_file.to_string_lossy(),
+            removed: &removed_file.to_string_lossy(),
+        },
+        None,
+        None,
+    )
+    .unwrap();
+    changeset(
+        &old_file.to_string_lossy(),
+        &new_file.to_string_lossy(),
+        ChangesetOutput::RdfPatch(&patch_file.to_string_lossy()),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let mut expected = search(&new_file.to_string_lossy(), "?s ?p ?o").unwrap();
+    expected.sort();
+
+    let added_path = added_file.to_string_lossy().to_string();
+    let removed_path = removed_file.to_string_lossy().to_string();
+    let patch_path = patch_file.to_string_lossy().to_string();
+    let inputs = [
+        PatchInput::Cottas {
+            added: &added_path,
+            removed: &removed_path,
+        },
+        PatchInput::RdfPatch(&patch_path),
+    ];
+    for input in inputs {
+        let patched_file = temp_dir.path().join("patched.cottas");
+        patch(
+            &old_file.to_string_lossy(),
+            input,
+            &patched_file.to_string_lossy(),
+        )
+        .unwrap();
+
+        let mut patched = search(&patched_file.to_string_lossy(), "?s ?p ?o").unwrap();
+        patched.sort();
+        assert_eq!(patched, expected);
+        assert_eq!(info(&patched_file.to_string_lossy()).unwrap().index, "pos");
+    }
+}
+
+#[test]
+fn test_patch_drops_foreign_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let base_file = temp_dir.path().join("base.cottas");
+    let base_file = base_file.to_string_lossy();
+    let conn = connection_in_memory().unwrap();
+    conn.execute(
+        &format!(
+            "COPY (SELECT '<http://ex/s>' AS s, '<http://ex/p>' AS p, '<http://ex/o>' AS o) TO '{}' \
+             (FORMAT PARQUET, KV_METADATA {{index: 'spo', prefixes: 'PREFIX ex: <http://ex/>', \
+             \"ARROW:schema\": 'foreign'}})",
+            base_file
+        ),
+        [],
+    )
+    .unwrap();
+
+    let patch_file = temp_dir.path().join("changes.rdfp");
+    fs::write(
+        &patch_file,
+        "A <http://ex/s> <http://ex/p> <http://ex/added> .\n",
+    )
+    .unwrap();
+    let patched_file = temp_dir.path().join("patched.cottas");
+    let patched_file = patched_file.to_string_lossy();
+    patch(
+        &base_file,
+        PatchInput::RdfPatch(&patch_file.to_string_lossy()),
+        &patched_file,
+    )
+    .unwrap();
+
+    // Only the prefixes are inherited, next to the metadata of the patched file itself
+    let metadata = cottas_rs::duckdb::read_kv_metadata(&conn, &patched_file).unwrap();
+    assert_eq!(metadata["prefixes"], "PREFIX ex: <http://ex/>");
+    assert_eq!(metadata["index"], "spo");
+    assert!(!metadata.contains_key("ARROW:schema"));
+    assert_eq!(search(&patched_file, "?s ?p ?o").unwrap().len(), 2);
+}
+
+#[test]
+fn test_rdf_patch_aborted_transaction() {
+    let temp_dir = TempDir::new().unwrap();
+    let patch_file = temp_dir.path().join("changes.rdfp");
+    fs::write(
+        &patch_file,
+        "TX .\n\
+         A <http://ex/s> <http://ex/p> <http://ex/aborted> .\n\
+         TA .\n\
+         TX .\n\
+         A <http://ex/s> <http://ex/p> <http://ex/committed> .\n\
+         TC .\n",
+    )
+    .unwrap();
+
+    let rows = cottas_rs::parser::parse_rdf_patch(&patch_file.to_string_lossy())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].1 .2, "<http://ex/committed>");
+
+    // The rows of a transaction without `TC` are not applied
+    fs::write(
+        &patch_file,
+        "TX .\nA <http://ex/s> <http://ex/p> <http://ex/o> .\n",
+    )
+    .unwrap();
+    let rows = cottas_rs::parser::parse_rdf_patch(&patch_file.to_string_lossy())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>();
+    assert!(matches!(
+        rows,
+        Err(CottasError::Syntax { line: Some(2), .. })
+    ));
+}
+
+#[test]
+fn test_rdf_patch_prefixes() {
+    let temp_dir = TempDir::new().unwrap();
+    let patch_file = temp_dir.path().join("changes.rdfp");
+    fs::write(
+        &patch_file,
+        "PA ex: <http://ex/> .\n\
+         PA xsd <http://www.w3.org/2001/XMLSchema#> .\n\
+         TX .\n\
+         A ex:s ex:p \"1\"^^xsd:integer .\n\
+         D ex:s ex:p ex:o ex:g .\n\
+         TC .\n\
+         PD ex .\n\
+         A ex:s ex:p ex:o .\n",
+    )
+    .unwrap();
+
+    let mut rows = cottas_rs::parser::parse_rdf_patch(&patch_file.to_string_lossy()).unwrap();
+    let (operation, quad) = rows.next().unwrap().unwrap();
+    assert_eq!(operation, cottas_rs::parser::PatchOperation::Add);
+    assert_eq!(
+        quad,
+        (
+            "<http://ex/s>".to_string(),
+            "<http://ex/p>".to_string(),
+            "\"1\"^^<http://www.w3.org/2001/XMLSchema#integer>".to_string(),
+            None
+        )
+    );
+    let (operation, quad) = rows.next().unwrap().unwrap();
+    assert_eq!(operation, cottas_rs::parser::PatchOperation::Delete);
+    assert_eq!(quad.3.as_deref(), Some("<http://ex/g>"));
+
+    // `ex` is no longer declared
+    assert!(matches!(
+        rows.next(),
+        Some(Err(CottasError::Syntax { line: Some(8), .. }))
+    ));
+}
+
 #[test]
 fn test_info_real_cottas() {
     let source_file = "tests/data/example3.ttl";
@@ -280,6 +1731,9 @@ fn test_info_real_cottas() {
     assert!(info.distinct_subjects > 0);
     assert!(info.distinct_objects > 0);
     assert!(info.quads == false);
+    assert_eq!(info.index, "spo");
+    assert_eq!(info.format_version, cottas_rs::utils::COTTAS_FORMAT_VERSION);
+    assert!(info.library_version.starts_with("cottas-rs"));
 }
 
 #[test]
@@ -287,3 +1741,263 @@ fn test_verify_valid_cottas() {
     let result = verify("tests/data/example.cottas").unwrap();
     assert!(result, "Should be a valid cottas file");
 }
+
+#[test]
+fn test_typed_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let target_file = temp_dir.path().join("out.cottas");
+    let target_file = target_file.to_string_lossy();
+
+    assert!(matches!(
+        rdf2_cottas("tests/data/example.ttl", &target_file, "sps"),
+        Err(CottasError::InvalidIndex { .. })
+    ));
+    assert!(matches!(
+        cottas_rs::parser::parse_tp("?s ?p"),
+        Err(CottasError::InvalidPattern { .. })
+    ));
+    assert!(matches!(
+        info("tests/data/example.ttl"),
+        Err(CottasError::InvalidCottasFile { .. })
+    ));
+
+    let invalid_file = temp_dir.path().join("invalid.nt");
+    fs::write(
+        &invalid_file,
+        "<http://ex/a> <http://ex/p> <http://ex/b> .\n<http://ex/a> <http://ex/p> .\n",
+    )
+    .unwrap();
+    match rdf2_cottas(&invalid_file.to_string_lossy(), &target_file, "spo") {
+        Err(CottasError::Syntax { line, .. }) => assert_eq!(line, Some(2)),
+        other => panic!("Expected a syntax error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cottas_file_handle() {
+    let cottas_file = "tests/data/example.cottas";
+    let file = CottasFile::open(cottas_file).unwrap();
+
+    assert!(!file.quad_mode());
+    assert!(file.verify().unwrap());
+    assert_eq!(file.info().unwrap().triples, 3);
+
+    let pattern = "?s <http://example.org/knows> ?o";
+    let mut results = file.search(pattern).unwrap();
+    let mut expected = search(cottas_file, pattern).unwrap();
+    results.sort();
+    expected.sort();
+    assert_eq!(results, expected);
+    assert_eq!(file.count(pattern).unwrap(), 3);
+    assert!(file.estimate_count(pattern).unwrap() >= 3);
+
+    // Clones share the connection and can be used from other threads
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let file = file.clone();
+            std::thread::spawn(move || file.count("?s ?p ?o").unwrap())
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 3);
+    }
+
+    let mut exported = Vec::new();
+    file.export(&mut exported, None, None).unwrap();
+    let mut written = Vec::new();
+    cottas2_rdf_writer(cottas_file, &mut written, None, None).unwrap();
+    assert_eq!(exported, written);
+
+    assert!(matches!(
+        CottasFile::open("tests/data/example.ttl"),
+        Err(CottasError::InvalidCottasFile { .. })
+    ));
+}
+
+#[test]
+fn test_hostile_file_names_and_patterns() {
+    let temp_dir = TempDir::new().unwrap();
+    let hostile = |name: &str| {
+        temp_dir
+            .path()
+            .join(format!("it's'); DROP TABLE x; --{}", name))
+            .to_string_lossy()
+            .to_string()
+    };
+
+    let source = hostile("source.ttl");
+    fs::copy("tests/data/example.ttl", &source).unwrap();
+    let cottas_file = hostile("example.cottas");
+    rdf2_cottas(&source, &cottas_file, "spo").unwrap();
+
+    assert!(verify(&cottas_file).unwrap());
+    assert_eq!(info(&cottas_file).unwrap().triples, 3);
+    assert_eq!(search(&cottas_file, "?s ?p ?o").unwrap().len(), 3);
+    assert_eq!(
+        estimate_count_in_duckdb(&cottas_file, "?s ?p ?o").unwrap(),
+        3
+    );
+
+    // A distinct second input whose name holds the placeholders of the set operations
+    let second_file = hostile("second {1} {2}.cottas");
+    fs::copy("tests/data/example.cottas", &second_file).unwrap();
+
+    let cat_file = hostile("cat.cottas");
+    cat(
+        &[cottas_file.clone(), second_file.clone()],
+        &cat_file,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(search(&cat_file, "?s ?p ?o").unwrap().len(), 3);
+
+    let intersect_file = hostile("intersect {2}.cottas");
+    intersect(&cottas_file, &second_file, &intersect_file, None, None).unwrap();
+    assert_eq!(search(&intersect_file, "?s ?p ?o").unwrap().len(), 3);
+
+    let symdiff_file = hostile("symdiff {2}.cottas");
+    symdiff(&second_file, &cottas_file, &symdiff_file, None, None).unwrap();
+    assert!(search(&symdiff_file, "?s ?p ?o").unwrap().is_empty());
+
+    let diff_file = hostile("diff.cottas");
+    diff(&cat_file, &cottas_file, &diff_file, None, None).unwrap();
+    assert!(search(&diff_file, "?s ?p ?o").unwrap().is_empty());
+
+    let rdf_file = hostile("out.nt");
+    cottas2_rdf(&cottas_file, &rdf_file).unwrap();
+    assert_eq!(fs::read_to_string(&rdf_file).unwrap().lines().count(), 3);
+
+    // Terms with quotes match nothing instead of breaking out of the SQL literal
+    let files = [cottas_file.clone()];
+    for pattern in [
+        "?s ?p \"x' OR '1'='1\"",
+        "?s ?p \"x'); DROP TABLE quads; --\"",
+        "?s <http://ex/p');DROP_TABLE_quads;--> ?o",
+    ] {
+        assert!(search(&cottas_file, pattern).unwrap().is_empty());
+        assert!(bgp(&files, pattern).unwrap().is_empty());
+    }
+
+    // IRIs with spaces are rejected before any SQL is built
+    let pattern = "?s <http://ex/p'); DROP TABLE quads; --> ?o";
+    assert!(matches!(
+        search(&cottas_file, pattern),
+        Err(CottasError::InvalidPattern { .. })
+    ));
+    assert!(matches!(
+        bgp(&files, pattern),
+        Err(CottasError::InvalidPattern { .. })
+    ));
+}
+
+#[test]
+fn test_write_options() {
+    let temp_dir = TempDir::new().unwrap();
+    let cottas_file = temp_dir.path().join("snappy.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+
+    let options = WriteOptions {
+        codec: ParquetCodec::Snappy,
+        row_group_size: Some(2),
+        dictionary_page_size_limit: Some(4096),
+        parquet_version: ParquetVersion::V1,
+        dictionary_encoding: false,
+        ..WriteOptions::default()
+    };
+    rdf2_cottas_with_options("tests/data/example.ttl", &cottas_file, "spo", &options).unwrap();
+
+    let info = info(&cottas_file).unwrap();
+    assert_eq!(info.compression.to_uppercase(), "SNAPPY");
+    assert_eq!(info.triples, 3);
+    assert_eq!(search(&cottas_file, "?s ?p ?o").unwrap().len(), 3);
+
+    // Only Zstandard has compression levels
+    let options = WriteOptions {
+        compression_level: Some(3),
+        ..options
+    };
+    assert!(matches!(
+        rdf2_cottas_with_options("tests/data/example.ttl", &cottas_file, "spo", &options),
+        Err(CottasError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_touched_row_groups() {
+    let temp_dir = TempDir::new().unwrap();
+    let rdf_file = temp_dir.path().join("many.nt");
+    let cottas_file = temp_dir.path().join("many.cottas");
+    let cottas_file = cottas_file.to_string_lossy();
+
+    let triples: String = (0..10_000)
+        .map(|i| format!("<http://ex/s{:05}> <http://ex/p{}> \"{}\" .\n", i, i % 3, i))
+        .collect();
+    fs::write(&rdf_file, triples).unwrap();
+
+    let options = WriteOptions {
+        row_group_size: Some(2048),
+        ..WriteOptions::default()
+    };
+    rdf2_cottas_with_options(&rdf_file.to_string_lossy(), &cottas_file, "spo", &options).unwrap();
+
+    let row_groups = info(&cottas_file).unwrap().triples_groups;
+    assert!(row_groups > 1);
+
+    // A bound subject only touches the row group holding it
+    let pattern = "<http://ex/s04242> ?p ?o";
+    assert_eq!(
+        info_with_pattern(&cottas_file, pattern)
+            .unwrap()
+            .touched_row_groups,
+        Some(1)
+    );
+    assert_eq!(
+        search(&cottas_file, pattern).unwrap(),
+        vec![vec![
+            "<http://ex/s04242>".to_string(),
+            "<http://ex/p0>".to_string(),
+            "\"4242\"".to_string()
+        ]]
+    );
+
+    // A subject outside the min/max statistics touches no row group
+    let pattern = "<http://ex/missing> ?p ?o";
+    assert_eq!(
+        info_with_pattern(&cottas_file, pattern)
+            .unwrap()
+            .touched_row_groups,
+        Some(0)
+    );
+    assert!(search(&cottas_file, pattern).unwrap().is_empty());
+
+    let file = CottasFile::open(&cottas_file).unwrap();
+    assert_eq!(file.info().unwrap().touched_row_groups, None);
+    assert_eq!(
+        file.info_with_pattern("<http://ex/s00007> ?p ?o")
+            .unwrap()
+            .touched_row_groups,
+        Some(1)
+    );
+    assert_eq!(
+        file.info_with_pattern("?s ?p ?o")
+            .unwrap()
+            .touched_row_groups,
+        Some(row_groups)
+    );
+    assert_eq!(
+        file.search("<http://ex/s00007> ?p ?o").unwrap(),
+        vec![vec![
+            "<http://ex/s00007>".to_string(),
+            "<http://ex/p1>".to_string(),
+            "\"7\"".to_string()
+        ]]
+    );
+    assert_eq!(
+        file.page("<http://ex/s09999> ?p ?o", 0, 10).unwrap().len(),
+        1
+    );
+    assert_eq!(file.count("?s <http://ex/p1> ?o").unwrap(), 3333);
+    assert_eq!(file.count("<http://ex/s00007> ?p ?o").unwrap(), 1);
+    assert_eq!(file.estimate_count("<http://ex/missing> ?p ?o").unwrap(), 0);
+}